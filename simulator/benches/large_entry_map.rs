@@ -0,0 +1,126 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! How the size of `ledger_entries` affects a simulation.
+//!
+//! Calls `add` from `tests/fixtures/add.wasm` through [`direct_invoke`],
+//! once with only the contract's own entries and once with 20,000
+//! unrelated contract data entries alongside them. Each case runs
+//! `ITERATIONS` times after one warm-up run and reports the median wall
+//! time of the whole call, and of the storage load and invocation phases
+//! from the response's `timings`, in microseconds. It also prints the
+//! metered CPU instructions, which must not change with the map's size:
+//! entries are decoded once up front and read by hash lookup.
+//!
+//! Run with `cargo bench --bench large_entry_map`.
+
+use base64::Engine;
+use simulator::{direct_invoke, local_contract_entries};
+use soroban_env_host::xdr::{
+    ContractDataDurability, ContractDataEntry, ContractId, ExtensionPoint, Hash, LedgerEntry,
+    LedgerEntryData, LedgerEntryExt, LedgerKey, LedgerKeyContractData, Limits, ScAddress, ScVal,
+    WriteXdr,
+};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const CONTRACT_ID: &str = "CA3D5KRYM6CB7OWQ6TWYRR3Z4T7GNZLKERYNZGGA5SOAOPIFY6YQGAXE";
+const FILLER_ENTRIES: u32 = 20_000;
+const ITERATIONS: usize = 20;
+
+/// Median of one case's measurements.
+struct Sample {
+    wall: Duration,
+    storage_load: Duration,
+    invoke: Duration,
+    cpu_instructions: u64,
+}
+
+/// `count` persistent entries of a contract the invocation never touches.
+fn filler_entries(count: u32) -> HashMap<String, String> {
+    let encode = |xdr: Vec<u8>| base64::engine::general_purpose::STANDARD.encode(xdr);
+    let other = ScAddress::Contract(ContractId(Hash([0xd0; 32])));
+    (0..count)
+        .map(|i| {
+            let key = LedgerKey::ContractData(LedgerKeyContractData {
+                contract: other.clone(),
+                key: ScVal::U32(i),
+                durability: ContractDataDurability::Persistent,
+            });
+            let entry = LedgerEntry {
+                last_modified_ledger_seq: 1,
+                data: LedgerEntryData::ContractData(ContractDataEntry {
+                    ext: ExtensionPoint::V0,
+                    contract: other.clone(),
+                    key: ScVal::U32(i),
+                    durability: ContractDataDurability::Persistent,
+                    val: ScVal::U32(i),
+                }),
+                ext: LedgerEntryExt::V0,
+            };
+            (
+                encode(key.to_xdr(Limits::none()).unwrap()),
+                encode(entry.to_xdr(Limits::none()).unwrap()),
+            )
+        })
+        .collect()
+}
+
+/// Runs `add(2, 40)` against `entries` and returns the median measurements.
+fn measure(entries: &HashMap<String, String>) -> Sample {
+    let request = serde_json::json!({
+        "mode": "direct_invoke",
+        "contract_id": CONTRACT_ID,
+        "function": "add",
+        "args": [{"type": "u32", "value": 2}, {"type": "u32", "value": 40}],
+        "ledger_entries": entries,
+    });
+    let mut runs: Vec<Sample> = (0..=ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            let response = direct_invoke(request.clone());
+            let wall = start.elapsed();
+            assert_eq!(response.status, "success", "error: {:?}", response.error);
+            let timings = response.timings.expect("response has no timings");
+            Sample {
+                wall,
+                storage_load: Duration::from_nanos(timings.storage_load_ns),
+                invoke: Duration::from_nanos(timings.invoke_ns),
+                cpu_instructions: response.resources.map_or(0, |r| r.cpu_instructions),
+            }
+        })
+        .skip(1)
+        .collect();
+    let median = |key: fn(&Sample) -> Duration, runs: &mut Vec<Sample>| {
+        runs.sort_by_key(key);
+        key(&runs[runs.len() / 2])
+    };
+    Sample {
+        wall: median(|s| s.wall, &mut runs),
+        storage_load: median(|s| s.storage_load, &mut runs),
+        invoke: median(|s| s.invoke, &mut runs),
+        cpu_instructions: runs[0].cpu_instructions,
+    }
+}
+
+fn main() {
+    let wasm = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/add.wasm"))
+        .expect("failed to read add.wasm");
+    let small = local_contract_entries(&wasm, CONTRACT_ID).unwrap();
+    let mut large = filler_entries(FILLER_ENTRIES);
+    large.extend(small.clone());
+
+    println!("median of {ITERATIONS} runs, microseconds");
+    println!("{:>8} {:>10} {:>10} {:>10} {:>12}", "entries", "total", "load", "invoke", "cpu insns");
+    for entries in [&small, &large] {
+        let sample = measure(entries);
+        println!(
+            "{:>8} {:>10} {:>10} {:>10} {:>12}",
+            entries.len(),
+            sample.wall.as_micros(),
+            sample.storage_load.as_micros(),
+            sample.invoke.as_micros(),
+            sample.cpu_instructions
+        );
+    }
+}
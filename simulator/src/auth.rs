@@ -0,0 +1,449 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Attribution of authorization failures to individual auth entries.
+//!
+//! When an invocation fails authorization the host reports a single error
+//! without saying which `SorobanAuthorizationEntry` was at fault. This module
+//! re-checks each entry of the failed operation the way the host does for
+//! account signers: expiration, nonce reuse, ed25519 signatures over the
+//! authorization payload, and, when the account is in the snapshot, that the
+//! signing keys belong to it and their weights meet its medium threshold.
+//! Contract signers run their own `__check_auth`, so only their expiration
+//! and nonce are checked here.
+//!
+//! It also turns the payloads the host records in recording-auth mode into
+//! the unsigned entries a client needs to sign.
+
+use crate::scval_json::{executable_json, scval_to_json};
+use crate::snapshot::LedgerSnapshot;
+use crate::strkey::sc_address_strkey;
+use crate::types::{AuthCredentialsJson, AuthErrorJson, RecordedAuthJson};
+use base64::Engine as _;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use soroban_env_host::auth::RecordedAuthPayload;
+use soroban_env_host::xdr::{
+    AccountId, ContractDataDurability, Hash, HashIdPreimage, HashIdPreimageSorobanAuthorization,
+    LedgerEntryData, LedgerKey, LedgerKeyAccount, LedgerKeyContractData, Limits, PublicKey,
+    ScAddress, ScNonceKey, ScVal, SignerKey, SorobanAddressCredentials, SorobanAuthorizationEntry,
+    SorobanAuthorizedFunction, SorobanAuthorizedInvocation, SorobanCredentials, Uint256, WriteXdr,
+};
+
+/// Ledger state an authorization entry is checked against.
+pub struct AuthContext<'a> {
+    pub network_id: [u8; 32],
+    pub sequence_number: u32,
+    pub snapshot: &'a LedgerSnapshot,
+}
+
+/// Problems found in the `entries` of operation `operation_index`, one per
+/// faulty entry. `host_error` is reported as an `unauthorized` failure with
+/// no entry when every entry checks out, since then the invocation needed an
+/// authorization nobody supplied.
+pub fn auth_errors(
+    entries: &[SorobanAuthorizationEntry],
+    operation_index: usize,
+    context: &AuthContext,
+    host_error: &str,
+) -> Vec<AuthErrorJson> {
+    let mut errors: Vec<AuthErrorJson> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(entry_index, entry)| {
+            let SorobanCredentials::Address(credentials) = &entry.credentials else {
+                return None;
+            };
+            let (reason, detail) = check_address_entry(entry, credentials, context)?;
+            Some(AuthErrorJson {
+                operation_index,
+                entry_index: Some(entry_index),
+                signer: Some(sc_address_strkey(&credentials.address)),
+                reason: reason.to_string(),
+                detail,
+            })
+        })
+        .collect();
+
+    if errors.is_empty() {
+        errors.push(AuthErrorJson {
+            operation_index,
+            entry_index: None,
+            signer: None,
+            reason: "unauthorized".to_string(),
+            detail: host_error.to_string(),
+        });
+    }
+    errors
+}
+
+/// The first problem with an address-credentialed entry, as a reason code
+/// and a human-readable detail.
+fn check_address_entry(
+    entry: &SorobanAuthorizationEntry,
+    credentials: &SorobanAddressCredentials,
+    context: &AuthContext,
+) -> Option<(&'static str, String)> {
+    if credentials.signature_expiration_ledger < context.sequence_number {
+        return Some((
+            "expired",
+            format!(
+                "signature expired at ledger {}, simulating ledger {}",
+                credentials.signature_expiration_ledger, context.sequence_number
+            ),
+        ));
+    }
+    if nonce_consumed(credentials, context.snapshot) {
+        return Some((
+            "nonce_reused",
+            format!("nonce {} has already been used", credentials.nonce),
+        ));
+    }
+
+    let ScAddress::Account(account) = &credentials.address else {
+        return None;
+    };
+    let PublicKey::PublicKeyTypeEd25519(account_key) = &account.0;
+    let signatures = match account_signatures(&credentials.signature) {
+        Ok(signatures) if signatures.is_empty() => {
+            return Some(("missing_signature", "entry carries no signatures".to_string()));
+        }
+        Ok(signatures) => signatures,
+        Err(e) => return Some(("bad_signature", e)),
+    };
+
+    if signatures.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+        return Some((
+            "bad_signature",
+            "signatures must be ordered by public key, one per key".to_string(),
+        ));
+    }
+
+    let payload = signature_payload(entry, credentials, context.network_id)?;
+    for (public_key, signature) in &signatures {
+        let verified = VerifyingKey::from_bytes(public_key)
+            .ok()
+            .zip(Signature::from_slice(signature).ok())
+            .is_some_and(|(key, sig)| key.verify(&payload, &sig).is_ok());
+        if !verified {
+            return Some((
+                "bad_signature",
+                format!(
+                    "signature by {} does not match the authorization payload",
+                    key_name(public_key, account_key)
+                ),
+            ));
+        }
+    }
+    check_signer_weights(account, &signatures, context.snapshot)
+}
+
+/// How a detail message refers to `public_key` of the account whose master
+/// key is `account_key`.
+fn key_name(public_key: &[u8; 32], account_key: &Uint256) -> String {
+    if *public_key == account_key.0 {
+        "the account's master key".to_string()
+    } else {
+        format!("key {}", hex::encode(public_key))
+    }
+}
+
+/// The first problem with the weight of the keys that signed for `account`:
+/// each must be one of its signers, and together they must meet its medium
+/// threshold. An account the snapshot does not hold is not checked.
+fn check_signer_weights(
+    account: &AccountId,
+    signatures: &[KeySignature],
+    snapshot: &LedgerSnapshot,
+) -> Option<(&'static str, String)> {
+    let key = LedgerKey::Account(LedgerKeyAccount {
+        account_id: account.clone(),
+    })
+    .to_xdr(Limits::none())
+    .ok()?;
+    let entry = snapshot.load(&key).ok()??;
+    let LedgerEntryData::Account(account_entry) = &entry.data else {
+        return None;
+    };
+    let PublicKey::PublicKeyTypeEd25519(account_key) = &account.0;
+    let [master_weight, _, medium_threshold, _] = account_entry.thresholds.0;
+
+    let mut weight = 0u32;
+    for (public_key, _) in signatures {
+        let signer_weight = if *public_key == account_key.0 {
+            u32::from(master_weight)
+        } else {
+            account_entry
+                .signers
+                .iter()
+                .find(|signer| signer.key == SignerKey::Ed25519(Uint256(*public_key)))
+                // Weights above 255 predate protocol 10 and count as 255.
+                .map_or(0, |signer| signer.weight.min(u32::from(u8::MAX)))
+        };
+        if signer_weight == 0 {
+            return Some((
+                "unknown_signer",
+                format!(
+                    "{} is not a signer of the account",
+                    key_name(public_key, account_key)
+                ),
+            ));
+        }
+        weight += signer_weight;
+    }
+    if weight < u32::from(medium_threshold) {
+        return Some((
+            "insufficient_weight",
+            format!(
+                "signature weight {weight} is below the account's medium threshold \
+                 {medium_threshold}"
+            ),
+        ));
+    }
+    None
+}
+
+/// Whether the nonce's ledger entry already exists, i.e. an earlier
+/// transaction consumed it.
+fn nonce_consumed(credentials: &SorobanAddressCredentials, snapshot: &LedgerSnapshot) -> bool {
+    let key = LedgerKey::ContractData(LedgerKeyContractData {
+        contract: credentials.address.clone(),
+        key: ScVal::LedgerKeyNonce(ScNonceKey {
+            nonce: credentials.nonce,
+        }),
+        durability: ContractDataDurability::Temporary,
+    });
+    key.to_xdr(Limits::none())
+        .is_ok_and(|bytes| matches!(snapshot.load(&bytes), Ok(Some(_))))
+}
+
+/// SHA-256 of the `HashIdPreimage` an account signer signs.
+fn signature_payload(
+    entry: &SorobanAuthorizationEntry,
+    credentials: &SorobanAddressCredentials,
+    network_id: [u8; 32],
+) -> Option<[u8; 32]> {
+    let preimage = HashIdPreimage::SorobanAuthorization(HashIdPreimageSorobanAuthorization {
+        network_id: Hash(network_id),
+        nonce: credentials.nonce,
+        signature_expiration_ledger: credentials.signature_expiration_ledger,
+        invocation: entry.root_invocation.clone(),
+    });
+    let bytes = preimage.to_xdr(Limits::none()).ok()?;
+    Some(Sha256::digest(bytes).into())
+}
+
+/// An ed25519 public key and the signature made with it.
+type KeySignature = ([u8; 32], Vec<u8>);
+
+/// `(public_key, signature)` pairs from an account signature value: a vec
+/// of `{public_key: bytes, signature: bytes}` maps.
+fn account_signatures(signature: &ScVal) -> Result<Vec<KeySignature>, String> {
+    let items = match signature {
+        ScVal::Void => return Ok(vec![]),
+        ScVal::Vec(Some(items)) => items,
+        other => return Err(format!("signature must be a vec, got {}", other.name())),
+    };
+    items
+        .iter()
+        .map(|item| {
+            let ScVal::Map(Some(fields)) = item else {
+                return Err("each signature must be a map".to_string());
+            };
+            let field = |name: &str| {
+                fields.iter().find_map(|entry| match (&entry.key, &entry.val) {
+                    (ScVal::Symbol(key), ScVal::Bytes(bytes))
+                        if key.to_utf8_string_lossy() == name =>
+                    {
+                        Some(bytes.to_vec())
+                    }
+                    _ => None,
+                })
+            };
+            let public_key = field("public_key")
+                .and_then(|key| <[u8; 32]>::try_from(key).ok())
+                .ok_or("signature is missing a 32-byte public_key")?;
+            let signature = field("signature").ok_or("signature is missing its signature bytes")?;
+            Ok((public_key, signature))
+        })
+        .collect()
+}
+
+/// The unsigned entry for a payload the host recorded, as reported in
+/// `recorded_auth`.
+pub fn recorded_auth_json(
+    payload: &RecordedAuthPayload,
+    operation_index: usize,
+) -> Result<RecordedAuthJson, String> {
+    let credentials = match (&payload.address, payload.nonce) {
+        (Some(address), Some(nonce)) => SorobanCredentials::Address(SorobanAddressCredentials {
+            address: address.clone(),
+            nonce,
+            signature_expiration_ledger: 0,
+            signature: ScVal::Void,
+        }),
+        _ => SorobanCredentials::SourceAccount,
+    };
+    let entry = SorobanAuthorizationEntry {
+        credentials,
+        root_invocation: payload.invocation.clone(),
+    };
+    let entry_xdr = entry
+        .to_xdr(Limits::none())
+        .map_err(|e| format!("Failed to encode recorded auth entry: {e}"))?;
+    Ok(RecordedAuthJson {
+        operation_index,
+        entry_xdr: base64::engine::general_purpose::STANDARD.encode(entry_xdr),
+        credentials: credentials_json(&entry.credentials),
+        address: payload.address.as_ref().map(sc_address_strkey),
+        nonce: payload.nonce,
+        invocation: invocation_json(&payload.invocation),
+    })
+}
+
+/// Decode `credentials`, telling account signers from contract signers.
+pub fn credentials_json(credentials: &SorobanCredentials) -> AuthCredentialsJson {
+    match credentials {
+        SorobanCredentials::SourceAccount => AuthCredentialsJson {
+            credential_type: "source_account".to_string(),
+            address: None,
+            nonce: None,
+            signature_expiration_ledger: None,
+        },
+        SorobanCredentials::Address(credentials) => {
+            let credential_type = match &credentials.address {
+                ScAddress::Contract(_) => "contract_address",
+                _ => "account_address",
+            };
+            AuthCredentialsJson {
+                credential_type: credential_type.to_string(),
+                address: Some(sc_address_strkey(&credentials.address)),
+                nonce: Some(credentials.nonce),
+                signature_expiration_ledger: Some(credentials.signature_expiration_ledger),
+            }
+        }
+    }
+}
+
+/// Render an authorized invocation tree as JSON, with arguments in the
+/// tagged `ScVal` form.
+pub fn invocation_json(invocation: &SorobanAuthorizedInvocation) -> Value {
+    let function = match &invocation.function {
+        SorobanAuthorizedFunction::ContractFn(args) => json!({
+            "type": "contract_fn",
+            "contract": sc_address_strkey(&args.contract_address),
+            "function_name": args.function_name.to_utf8_string_lossy(),
+            "args": args.args.iter().map(scval_to_json).collect::<Vec<_>>(),
+        }),
+        SorobanAuthorizedFunction::CreateContractHostFn(args) => json!({
+            "type": "create_contract",
+            "executable": executable_json(&args.executable),
+        }),
+        SorobanAuthorizedFunction::CreateContractV2HostFn(args) => json!({
+            "type": "create_contract",
+            "executable": executable_json(&args.executable),
+            "constructor_args": args
+                .constructor_args
+                .iter()
+                .map(scval_to_json)
+                .collect::<Vec<_>>(),
+        }),
+    };
+    json!({
+        "function": function,
+        "sub_invocations": invocation
+            .sub_invocations
+            .iter()
+            .map(invocation_json)
+            .collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_env_host::xdr::{VecM};
+    use soroban_env_host::xdr::{ContractId, InvokeContractArgs, ScSymbol};
+
+    fn entry(signature_expiration_ledger: u32) -> SorobanAuthorizationEntry {
+        SorobanAuthorizationEntry {
+            credentials: SorobanCredentials::Address(SorobanAddressCredentials {
+                address: ScAddress::Account(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(
+                    [3u8; 32],
+                )))),
+                nonce: 1,
+                signature_expiration_ledger,
+                signature: ScVal::Void,
+            }),
+            root_invocation: SorobanAuthorizedInvocation {
+                function: SorobanAuthorizedFunction::ContractFn(InvokeContractArgs {
+                    contract_address: ScAddress::Contract(ContractId(Hash([1u8; 32]))),
+                    function_name: ScSymbol("transfer".try_into().unwrap()),
+                    args: VecM::default(),
+                }),
+                sub_invocations: VecM::default(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_expired_and_unsigned_entries_are_attributed() {
+        let snapshot = LedgerSnapshot::new();
+        let context = AuthContext {
+            network_id: [0u8; 32],
+            sequence_number: 100,
+            snapshot: &snapshot,
+        };
+        let errors = auth_errors(&[entry(50), entry(200)], 2, &context, "host error");
+        let reasons: Vec<_> = errors
+            .iter()
+            .map(|e| (e.entry_index, e.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![(Some(0), "expired"), (Some(1), "missing_signature")]
+        );
+        assert!(errors.iter().all(|e| e.operation_index == 2));
+        assert!(errors[0].signer.as_deref().unwrap().starts_with('G'));
+    }
+
+    #[test]
+    fn test_credentials_distinguish_signer_kinds() {
+        let source = credentials_json(&SorobanCredentials::SourceAccount);
+        assert_eq!(source.credential_type, "source_account");
+        assert_eq!(source.address, None);
+        assert_eq!(source.nonce, None);
+
+        let account = credentials_json(&entry(50).credentials);
+        assert_eq!(account.credential_type, "account_address");
+        assert!(account.address.as_deref().unwrap().starts_with('G'));
+        assert_eq!(account.nonce, Some(1));
+        assert_eq!(account.signature_expiration_ledger, Some(50));
+
+        let contract = credentials_json(&SorobanCredentials::Address(SorobanAddressCredentials {
+            address: ScAddress::Contract(ContractId(Hash([2u8; 32]))),
+            nonce: -7,
+            signature_expiration_ledger: 900,
+            signature: ScVal::Void,
+        }));
+        assert_eq!(contract.credential_type, "contract_address");
+        assert!(contract.address.as_deref().unwrap().starts_with('C'));
+        assert_eq!(contract.nonce, Some(-7));
+        assert_eq!(contract.signature_expiration_ledger, Some(900));
+    }
+
+    #[test]
+    fn test_no_faulty_entry_reports_unauthorized() {
+        let snapshot = LedgerSnapshot::new();
+        let context = AuthContext {
+            network_id: [0u8; 32],
+            sequence_number: 100,
+            snapshot: &snapshot,
+        };
+        let errors = auth_errors(&[], 0, &context, "Unauthorized function call");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].reason, "unauthorized");
+        assert_eq!(errors[0].entry_index, None);
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Recognition of classic (non-Soroban) operations.
+//!
+//! The host only executes Soroban operations. Classic ones in the same
+//! envelope are not applied, but are decoded here so users can see that
+//! the simulator noticed them and what they would have done.
+
+use crate::strkey::{account_strkey, muxed_account_strkey};
+use crate::types::ClassicOperation;
+use soroban_env_host::xdr::{
+    Asset, ChangeTrustAsset, Operation, OperationBody, OperationResult, OperationResultTr,
+};
+use std::collections::BTreeMap;
+
+/// Decode every classic operation in `operations`, keeping envelope indices.
+pub fn classic_operations(operations: &[Operation]) -> Vec<ClassicOperation> {
+    operations
+        .iter()
+        .enumerate()
+        .filter_map(|(index, op)| describe_classic_operation(index, op))
+        .collect()
+}
+
+/// Decode `op` into its type and key fields, or `None` for Soroban
+/// operations, which the simulator executes.
+pub fn describe_classic_operation(index: usize, op: &Operation) -> Option<ClassicOperation> {
+    let mut fields = BTreeMap::new();
+    let mut set = |name: &str, value: String| {
+        fields.insert(name.to_string(), value);
+    };
+
+    match &op.body {
+        OperationBody::InvokeHostFunction(_)
+        | OperationBody::ExtendFootprintTtl(_)
+        | OperationBody::RestoreFootprint(_) => return None,
+        OperationBody::CreateAccount(create) => {
+            set("destination", account_strkey(&create.destination));
+            set("starting_balance", create.starting_balance.to_string());
+        }
+        OperationBody::Payment(payment) => {
+            set("destination", muxed_account_strkey(&payment.destination));
+            set("asset", asset_string(&payment.asset));
+            set("amount", payment.amount.to_string());
+        }
+        OperationBody::PathPaymentStrictReceive(path) => {
+            set("destination", muxed_account_strkey(&path.destination));
+            set("send_asset", asset_string(&path.send_asset));
+            set("send_max", path.send_max.to_string());
+            set("dest_asset", asset_string(&path.dest_asset));
+            set("dest_amount", path.dest_amount.to_string());
+        }
+        OperationBody::PathPaymentStrictSend(path) => {
+            set("destination", muxed_account_strkey(&path.destination));
+            set("send_asset", asset_string(&path.send_asset));
+            set("send_amount", path.send_amount.to_string());
+            set("dest_asset", asset_string(&path.dest_asset));
+            set("dest_min", path.dest_min.to_string());
+        }
+        OperationBody::ChangeTrust(trust) => {
+            let asset = match &trust.line {
+                ChangeTrustAsset::Native => "native".to_string(),
+                ChangeTrustAsset::CreditAlphanum4(a) => {
+                    credit_string(&a.asset_code.0, &a.issuer)
+                }
+                ChangeTrustAsset::CreditAlphanum12(a) => {
+                    credit_string(&a.asset_code.0, &a.issuer)
+                }
+                ChangeTrustAsset::PoolShare(_) => "pool_share".to_string(),
+            };
+            set("asset", asset);
+            set("limit", trust.limit.to_string());
+        }
+        OperationBody::ManageSellOffer(offer) => {
+            set("selling", asset_string(&offer.selling));
+            set("buying", asset_string(&offer.buying));
+            set("amount", offer.amount.to_string());
+            set("price", format!("{}/{}", offer.price.n, offer.price.d));
+        }
+        OperationBody::ManageBuyOffer(offer) => {
+            set("selling", asset_string(&offer.selling));
+            set("buying", asset_string(&offer.buying));
+            set("buy_amount", offer.buy_amount.to_string());
+            set("price", format!("{}/{}", offer.price.n, offer.price.d));
+        }
+        OperationBody::AccountMerge(destination) => {
+            set("destination", muxed_account_strkey(destination));
+        }
+        _ => {}
+    }
+    if let Some(source) = &op.source_account {
+        set("source_account", muxed_account_strkey(source));
+    }
+
+    Some(ClassicOperation {
+        index,
+        operation_type: op.body.name().to_string(),
+        fields,
+        simulated: false,
+        recorded_result: None,
+    })
+}
+
+/// Fill in each operation's `recorded_result` from the network's
+/// per-operation `results`, which are in envelope order.
+pub fn attach_recorded_results(operations: &mut [ClassicOperation], results: &[OperationResult]) {
+    for op in operations {
+        op.recorded_result = results.get(op.index).and_then(result_code);
+    }
+}
+
+/// The XDR result code of `result`, e.g. `PAYMENT_UNDERFUNDED` or
+/// `OP_BAD_AUTH`. Payment, `CreateAccount`, `ChangeTrust` and `ManageData`
+/// results are decoded; other operation types give `None`.
+pub fn result_code(result: &OperationResult) -> Option<String> {
+    let OperationResult::OpInner(tr) = result else {
+        return Some(screaming_snake_case(result.name()));
+    };
+    let code = match tr {
+        OperationResultTr::Payment(r) => r.name(),
+        OperationResultTr::CreateAccount(r) => r.name(),
+        OperationResultTr::ChangeTrust(r) => r.name(),
+        OperationResultTr::ManageData(r) => r.name(),
+        _ => return None,
+    };
+    Some(format!(
+        "{}_{}",
+        screaming_snake_case(tr.name()),
+        screaming_snake_case(code)
+    ))
+}
+
+/// `LowReserve` to `LOW_RESERVE`, matching the XDR constant names.
+fn screaming_snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_uppercase());
+    }
+    out
+}
+
+/// `native`, or `CODE:ISSUER` for credit assets.
+pub fn asset_string(asset: &Asset) -> String {
+    match asset {
+        Asset::Native => "native".to_string(),
+        Asset::CreditAlphanum4(a) => credit_string(&a.asset_code.0, &a.issuer),
+        Asset::CreditAlphanum12(a) => credit_string(&a.asset_code.0, &a.issuer),
+    }
+}
+
+fn credit_string(code: &[u8], issuer: &soroban_env_host::xdr::AccountId) -> String {
+    let code: String = code
+        .iter()
+        .take_while(|&&b| b != 0)
+        .map(|&b| b as char)
+        .collect();
+    format!("{code}:{}", account_strkey(issuer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_env_host::xdr::{
+        AccountId, AlphaNum4, AssetCode4, MuxedAccount, PaymentOp, PublicKey, Uint256,
+    };
+
+    #[test]
+    fn test_result_codes_use_xdr_constant_names() {
+        use soroban_env_host::xdr::{
+            ChangeTrustResult, CreateAccountResult, ManageDataResult, PaymentResult,
+            SetOptionsResult,
+        };
+
+        let code = |tr| result_code(&OperationResult::OpInner(tr));
+        assert_eq!(
+            code(OperationResultTr::Payment(PaymentResult::Underfunded)).as_deref(),
+            Some("PAYMENT_UNDERFUNDED")
+        );
+        assert_eq!(
+            code(OperationResultTr::CreateAccount(CreateAccountResult::LowReserve)).as_deref(),
+            Some("CREATE_ACCOUNT_LOW_RESERVE")
+        );
+        assert_eq!(
+            code(OperationResultTr::ChangeTrust(ChangeTrustResult::Success)).as_deref(),
+            Some("CHANGE_TRUST_SUCCESS")
+        );
+        assert_eq!(
+            code(OperationResultTr::ManageData(ManageDataResult::NameNotFound)).as_deref(),
+            Some("MANAGE_DATA_NAME_NOT_FOUND")
+        );
+        assert_eq!(code(OperationResultTr::SetOptions(SetOptionsResult::Success)), None);
+        assert_eq!(
+            result_code(&OperationResult::OpBadAuth).as_deref(),
+            Some("OP_BAD_AUTH")
+        );
+    }
+
+    #[test]
+    fn test_payment_fields_are_decoded() {
+        let issuer = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([2u8; 32])));
+        let op = Operation {
+            source_account: None,
+            body: OperationBody::Payment(PaymentOp {
+                destination: MuxedAccount::Ed25519(Uint256([1u8; 32])),
+                asset: Asset::CreditAlphanum4(AlphaNum4 {
+                    asset_code: AssetCode4(*b"USD\0"),
+                    issuer: issuer.clone(),
+                }),
+                amount: 5_000_000,
+            }),
+        };
+
+        let described = describe_classic_operation(3, &op).expect("payment is classic");
+        assert_eq!(described.index, 3);
+        assert_eq!(described.operation_type, "Payment");
+        assert!(!described.simulated);
+        assert!(described.fields["destination"].starts_with('G'));
+        assert_eq!(
+            described.fields["asset"],
+            format!("USD:{}", account_strkey(&issuer))
+        );
+        assert_eq!(described.fields["amount"], "5000000");
+    }
+}
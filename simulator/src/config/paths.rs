@@ -17,9 +17,7 @@ use std::path::PathBuf;
 
 #[allow(dead_code)]
 pub fn theme_path() -> PathBuf {
-    let mut path = std::env::var("HOME")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| PathBuf::from("."));
+    let mut path = std::env::var("HOME").map_or_else(|_| PathBuf::from("."), PathBuf::from);
     path.push(".erst");
     path.push("theme.json");
     path
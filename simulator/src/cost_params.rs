@@ -0,0 +1,238 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Host cost model with per-cost-type overrides.
+//!
+//! Protocol upgrades retune the coefficients the host charges CPU
+//! instructions with. A request can replace individual coefficients to see
+//! how a proposed cost table would change its resource use and fee. The
+//! host does not expose its built-in table, so the defaults are read back
+//! by charging a scratch budget.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use soroban_env_host::budget::Budget;
+use soroban_env_host::xdr::{
+    ContractCostParamEntry, ContractCostParams, ContractCostType, ExtensionPoint, ScErrorCode,
+    ScErrorType,
+};
+use crate::types::ResourceCalibration;
+use soroban_env_host::HostError;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+/// Replacement CPU coefficients for one cost type, in the units of the
+/// network's `ContractCostParamEntry`.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy)]
+pub struct CostParamOverride {
+    /// Instructions charged per call, whatever the input size.
+    pub const_term: u64,
+    /// Instructions per unit of input, scaled by 128.
+    pub linear_term: u64,
+}
+
+/// Fractional bits of the fixed-point linear term: at an input of
+/// `1 << LINEAR_PROBE_SHIFT` it contributes exactly its own value.
+const LINEAR_PROBE_SHIFT: u32 = 7;
+
+/// Input size at which the linear term contributes exactly its own value.
+const LINEAR_PROBE_INPUT: u64 = 1 << LINEAR_PROBE_SHIFT;
+
+/// The host's built-in CPU and memory parameters, one entry per cost type
+/// in `ContractCostType` order. Every charge is made on a budget of its own
+/// and read back from the budget's tracker.
+fn default_cost_params(
+) -> Result<(Vec<ContractCostParamEntry>, Vec<ContractCostParamEntry>), HostError> {
+    let probe = |ty: ContractCostType, input: Option<u64>| {
+        let budget = Budget::default();
+        budget.charge(ty, input)?;
+        budget.get_tracker(ty)
+    };
+    let mut cpu = Vec::new();
+    let mut mem = Vec::new();
+    for ty in ContractCostType::VARIANTS {
+        // The host only accepts an input size for cost types that are linear
+        // in it; the others have no linear term to probe.
+        if Budget::default().get_tracker(ty)?.inputs.is_none() {
+            let fixed = probe(ty, None)?;
+            cpu.push(param_entry(fixed.cpu, 0));
+            mem.push(param_entry(fixed.mem, 0));
+            continue;
+        }
+        let fixed = probe(ty, Some(0))?;
+        // A few pairing and multi-scalar multiplication costs exceed the
+        // default limits at the full probe input, so the largest input that
+        // fits is probed and its linear cost scaled up.
+        let (shift, probed) = (0..=LINEAR_PROBE_SHIFT)
+            .rev()
+            .find_map(|shift| Some((shift, probe(ty, Some(1 << shift)).ok()?)))
+            .unwrap_or((LINEAR_PROBE_SHIFT, fixed));
+        let linear = |fixed: u64, probed: u64| {
+            probed.saturating_sub(fixed) << (LINEAR_PROBE_SHIFT - shift)
+        };
+        cpu.push(param_entry(fixed.cpu, linear(fixed.cpu, probed.cpu)));
+        mem.push(param_entry(fixed.mem, linear(fixed.mem, probed.mem)));
+    }
+    Ok((cpu, mem))
+}
+
+fn param_entry(const_term: u64, linear_term: u64) -> ContractCostParamEntry {
+    ContractCostParamEntry {
+        ext: ExtensionPoint::V0,
+        const_term: i64::try_from(const_term).unwrap_or(i64::MAX),
+        linear_term: i64::try_from(linear_term).unwrap_or(i64::MAX),
+    }
+}
+
+/// Replaces the CPU parameters of the hashing and signature cost types
+/// with a host-measured `calibration`. Per-byte costs are whole
+/// instructions, so they are scaled to the 7-bit fixed-point linear term.
+fn calibrate(cpu: &mut [ContractCostParamEntry], calibration: &ResourceCalibration) {
+    let mut set = |ty: ContractCostType, const_term: u64, linear_term: Option<u64>| {
+        let entry = &mut cpu[ty as usize];
+        entry.const_term = i64::try_from(const_term).unwrap_or(i64::MAX);
+        if let Some(linear_term) = linear_term {
+            let scaled = linear_term.saturating_mul(LINEAR_PROBE_INPUT);
+            entry.linear_term = i64::try_from(scaled).unwrap_or(i64::MAX);
+        }
+    };
+    set(
+        ContractCostType::ComputeSha256Hash,
+        calibration.sha256_fixed,
+        Some(calibration.sha256_per_byte),
+    );
+    set(
+        ContractCostType::ComputeKeccak256Hash,
+        calibration.keccak256_fixed,
+        Some(calibration.keccak256_per_byte),
+    );
+    set(ContractCostType::VerifyEd25519Sig, calibration.ed25519_fixed, None);
+}
+
+/// The default CPU and memory parameters with `calibration` applied.
+fn calibrated_cost_params(
+    calibration: Option<&ResourceCalibration>,
+) -> Result<(Vec<ContractCostParamEntry>, Vec<ContractCostParamEntry>), HostError> {
+    let (mut cpu, mem) = default_cost_params()?;
+    if let Some(calibration) = calibration {
+        calibrate(&mut cpu, calibration);
+    }
+    Ok((cpu, mem))
+}
+
+fn cost_params(entries: Vec<ContractCostParamEntry>) -> Result<ContractCostParams, HostError> {
+    entries
+        .try_into()
+        .map(ContractCostParams)
+        .map_err(|_| HostError::from((ScErrorType::Budget, ScErrorCode::InternalError)))
+}
+
+/// A budget with the given limits and the host's default cost model, with
+/// `calibration` applied if given.
+///
+/// # Errors
+/// Fails if the host rejects the cost model.
+pub fn budget_with_limits(
+    cpu_limit: u64,
+    mem_limit: u64,
+    calibration: Option<&ResourceCalibration>,
+) -> Result<Budget, HostError> {
+    let (cpu, mem) = calibrated_cost_params(calibration)?;
+    Budget::try_from_configs(cpu_limit, mem_limit, cost_params(cpu)?, cost_params(mem)?)
+}
+
+/// A budget with the given limits whose CPU model takes `overrides`.
+///
+/// Overrides are keyed by cost type name (e.g. `WasmInsnExec`) and replace
+/// the defaults. A `calibration` is applied first, so an override of the
+/// same cost type wins over it.
+///
+/// # Errors
+/// Names the unknown cost type, or says why the host rejected the model.
+pub fn budget_with_overrides<S: BuildHasher>(
+    overrides: &HashMap<String, CostParamOverride, S>,
+    calibration: Option<&ResourceCalibration>,
+    cpu_limit: u64,
+    mem_limit: u64,
+) -> Result<Budget, String> {
+    let (mut cpu, mem) = calibrated_cost_params(calibration)
+        .map_err(|e| format!("Failed to read the host's default cost model: {e:?}"))?;
+    for (name, params) in overrides {
+        let index = ContractCostType::VARIANTS
+            .iter()
+            .position(|ty| ty.name() == name)
+            .ok_or_else(|| format!("Unknown cost type '{name}' in cost_params_override"))?;
+        cpu[index] = param_entry(params.const_term, params.linear_term);
+    }
+    let params = |entries| {
+        cost_params(entries).map_err(|_| "Too many cost types for ContractCostParams".to_string())
+    };
+    Budget::try_from_configs(cpu_limit, mem_limit, params(cpu)?, params(mem)?)
+        .map_err(|e| format!("Host rejected the overridden cost model: {e:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpu_for(budget: &Budget, ty: ContractCostType, input: Option<u64>) -> u64 {
+        budget.charge(ty, input).unwrap();
+        budget.get_cpu_insns_consumed().unwrap()
+    }
+
+    #[test]
+    fn test_defaults_reproduce_the_host_model() {
+        let host_default = Budget::default();
+        let rebuilt = budget_with_overrides(&HashMap::new(), None, u64::MAX, u64::MAX).unwrap();
+        for (ty, input) in [
+            (ContractCostType::WasmInsnExec, None),
+            (ContractCostType::ComputeSha256Hash, Some(1000)),
+            (ContractCostType::Bls12381Pairing, Some(1)),
+        ] {
+            assert_eq!(cpu_for(&host_default, ty, input), cpu_for(&rebuilt, ty, input), "{ty:?}");
+        }
+    }
+
+    #[test]
+    fn test_budget_with_limits_enforces_them() {
+        let budget = budget_with_limits(1_000, u64::MAX, None).unwrap();
+        assert_eq!(budget.get_cpu_insns_remaining().unwrap(), 1_000);
+        assert!(budget.charge(ContractCostType::ComputeSha256Hash, Some(10_000)).is_err());
+    }
+
+    #[test]
+    fn test_overrides_win_over_calibration() {
+        let calibration = ResourceCalibration {
+            sha256_fixed: 1_000,
+            sha256_per_byte: 2,
+            keccak256_fixed: 3_000,
+            keccak256_per_byte: 4,
+            ed25519_fixed: 5_000,
+        };
+        let overrides = HashMap::from([(
+            "ComputeKeccak256Hash".to_string(),
+            CostParamOverride {
+                const_term: 7,
+                linear_term: 0,
+            },
+        )]);
+        let budget = budget_with_overrides(&overrides, Some(&calibration), u64::MAX, u64::MAX)
+            .unwrap();
+        assert_eq!(cpu_for(&budget, ContractCostType::ComputeSha256Hash, Some(10)), 1_020);
+        let before = budget.get_cpu_insns_consumed().unwrap();
+        assert_eq!(cpu_for(&budget, ContractCostType::ComputeKeccak256Hash, Some(10)) - before, 7);
+    }
+
+    #[test]
+    fn test_unknown_cost_type_is_rejected() {
+        let overrides = HashMap::from([(
+            "NotACostType".to_string(),
+            CostParamOverride {
+                const_term: 1,
+                linear_term: 0,
+            },
+        )]);
+        let err = budget_with_overrides(&overrides, None, 1, 1).unwrap_err();
+        assert!(err.contains("NotACostType"), "{err}");
+    }
+}
@@ -0,0 +1,358 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Decoding of single XDR blobs to JSON, for `--decode`.
+//!
+//! Lets users check what an envelope, key, entry, value or meta holds before
+//! simulating it. Values are rendered with the same decoders the simulator
+//! uses for its own output: tagged `ScVal` JSON, `StrKey` addresses and the
+//! classic-operation summaries.
+
+use crate::auth::{credentials_json, invocation_json};
+use crate::classic::describe_classic_operation;
+use crate::result_meta::{decode_transaction_meta, recorded_meta};
+use crate::scval_json::{executable_json, scval_to_json};
+use crate::strkey::{account_strkey, muxed_account_strkey, sc_address_strkey};
+use base64::Engine as _;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use soroban_env_host::xdr::{
+    AccountId, ContractDataDurability, FeeBumpTransactionInnerTx, HostFunction, LedgerEntry,
+    LedgerEntryData, LedgerKey, Operation, OperationBody, PublicKey, ReadXdr, ScVal,
+    TransactionEnvelope,
+};
+
+/// The kinds of blob `--decode` accepts.
+pub const KINDS: [&str; 5] = ["envelope", "ledger_key", "ledger_entry", "scval", "meta"];
+
+/// Decode base64 `blob` as XDR of `kind` (one of [`KINDS`]) into JSON.
+///
+/// # Errors
+/// Says why `blob` is not valid base64 XDR of `kind`, or that `kind` is
+/// unknown.
+pub fn decode_xdr(kind: &str, blob: &str) -> Result<Value, String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(blob.trim())
+        .map_err(|e| format!("Failed to decode base64: {e}"))?;
+    let parse_error = |e: soroban_env_host::xdr::Error| format!("Failed to parse {kind} XDR: {e}");
+    match kind {
+        "envelope" => TransactionEnvelope::from_xdr(bytes, crate::xdr_limits::limits())
+            .map(|envelope| envelope_json(&envelope))
+            .map_err(parse_error),
+        "ledger_key" => LedgerKey::from_xdr(bytes, crate::xdr_limits::limits())
+            .map(|key| ledger_key_json(&key))
+            .map_err(parse_error),
+        "ledger_entry" => LedgerEntry::from_xdr(bytes, crate::xdr_limits::limits())
+            .map(|entry| ledger_entry_json(&entry))
+            .map_err(parse_error),
+        "scval" => ScVal::from_xdr(bytes, crate::xdr_limits::limits())
+            .map(|val| scval_to_json(&val))
+            .map_err(parse_error),
+        "meta" => {
+            let meta = decode_transaction_meta(&bytes)?;
+            serde_json::to_value(recorded_meta(&meta)).map_err(|e| e.to_string())
+        }
+        other => Err(format!(
+            "Unknown XDR kind '{other}': expected one of {}",
+            KINDS.join(", ")
+        )),
+    }
+}
+
+fn envelope_json(envelope: &TransactionEnvelope) -> Value {
+    let (kind, source, fee, seq_num, operations): (_, _, i64, _, &[Operation]) = match envelope {
+        TransactionEnvelope::Tx(tx_v1) => (
+            "tx",
+            muxed_account_strkey(&tx_v1.tx.source_account),
+            tx_v1.tx.fee.into(),
+            tx_v1.tx.seq_num.0,
+            &tx_v1.tx.operations,
+        ),
+        TransactionEnvelope::TxV0(tx_v0) => (
+            "tx_v0",
+            account_strkey(&AccountId(PublicKey::PublicKeyTypeEd25519(
+                tx_v0.tx.source_account_ed25519.clone(),
+            ))),
+            tx_v0.tx.fee.into(),
+            tx_v0.tx.seq_num.0,
+            &tx_v0.tx.operations,
+        ),
+        TransactionEnvelope::TxFeeBump(bump) => match &bump.tx.inner_tx {
+            FeeBumpTransactionInnerTx::Tx(inner) => (
+                "fee_bump",
+                muxed_account_strkey(&inner.tx.source_account),
+                bump.tx.fee,
+                inner.tx.seq_num.0,
+                &inner.tx.operations,
+            ),
+        },
+    };
+    json!({
+        "type": kind,
+        "source_account": source,
+        "fee": fee,
+        "seq_num": seq_num.to_string(),
+        "operations": operations
+            .iter()
+            .enumerate()
+            .map(|(index, op)| operation_json(index, op))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn operation_json(index: usize, op: &Operation) -> Value {
+    let mut json = json!({
+        "index": index,
+        "type": op.body.name(),
+        "source_account": op.source_account.as_ref().map(muxed_account_strkey),
+    });
+    match &op.body {
+        OperationBody::InvokeHostFunction(invoke) => {
+            json["host_function"] = host_function_json(&invoke.host_function);
+            json["auth_entries"] = json!(invoke.auth.len());
+            json["auth"] = invoke
+                .auth
+                .iter()
+                .map(|entry| {
+                    json!({
+                        "credentials": credentials_json(&entry.credentials),
+                        "invocation": invocation_json(&entry.root_invocation),
+                    })
+                })
+                .collect();
+        }
+        OperationBody::ExtendFootprintTtl(extend) => {
+            json["extend_to"] = json!(extend.extend_to);
+        }
+        OperationBody::RestoreFootprint(_) => {}
+        _ => {
+            if let Some(classic) = describe_classic_operation(index, op) {
+                json["fields"] = json!(classic.fields);
+            }
+        }
+    }
+    json
+}
+
+fn host_function_json(host_function: &HostFunction) -> Value {
+    let args_json = |args: &[ScVal]| args.iter().map(scval_to_json).collect::<Vec<_>>();
+    match host_function {
+        HostFunction::InvokeContract(args) => json!({
+            "type": "invoke_contract",
+            "contract": sc_address_strkey(&args.contract_address),
+            "function_name": args.function_name.to_utf8_string_lossy(),
+            "args": args_json(&args.args),
+        }),
+        HostFunction::CreateContract(args) => json!({
+            "type": "create_contract",
+            "executable": executable_json(&args.executable),
+        }),
+        HostFunction::CreateContractV2(args) => json!({
+            "type": "create_contract",
+            "executable": executable_json(&args.executable),
+            "constructor_args": args_json(&args.constructor_args),
+        }),
+        HostFunction::UploadContractWasm(code) => json!({
+            "type": "upload_contract_wasm",
+            "wasm_hash": hex::encode(Sha256::digest(code.as_slice())),
+            "size": code.len(),
+        }),
+    }
+}
+
+const fn durability_str(durability: ContractDataDurability) -> &'static str {
+    match durability {
+        ContractDataDurability::Persistent => "persistent",
+        ContractDataDurability::Temporary => "temporary",
+    }
+}
+
+/// `"persistent"` or `"temporary"` for the contract data and code keys that
+/// have a durability, `None` for every other key.
+#[must_use]
+pub const fn key_durability(key: &LedgerKey) -> Option<&'static str> {
+    match key {
+        LedgerKey::ContractData(data) => Some(durability_str(data.durability)),
+        LedgerKey::ContractCode(_) => Some(durability_str(ContractDataDurability::Persistent)),
+        _ => None,
+    }
+}
+
+fn ledger_key_json(key: &LedgerKey) -> Value {
+    match key {
+        LedgerKey::ContractData(data) => json!({
+            "type": "contract_data",
+            "contract": sc_address_strkey(&data.contract),
+            "key": scval_to_json(&data.key),
+            "durability": durability_str(data.durability),
+        }),
+        LedgerKey::ContractCode(code) => json!({
+            "type": "contract_code",
+            "hash": hex::encode(code.hash.0),
+        }),
+        LedgerKey::Account(account) => json!({
+            "type": "account",
+            "account_id": account_strkey(&account.account_id),
+        }),
+        LedgerKey::Trustline(trustline) => json!({
+            "type": "trustline",
+            "account_id": account_strkey(&trustline.account_id),
+        }),
+        LedgerKey::Ttl(ttl) => json!({
+            "type": "ttl",
+            "key_hash": hex::encode(ttl.key_hash.0),
+        }),
+        other => json!({ "type": other.name() }),
+    }
+}
+
+fn ledger_entry_json(entry: &LedgerEntry) -> Value {
+    let data = match &entry.data {
+        LedgerEntryData::ContractData(data) => json!({
+            "type": "contract_data",
+            "contract": sc_address_strkey(&data.contract),
+            "key": scval_to_json(&data.key),
+            "durability": durability_str(data.durability),
+            "value": scval_to_json(&data.val),
+        }),
+        LedgerEntryData::ContractCode(code) => json!({
+            "type": "contract_code",
+            "hash": hex::encode(code.hash.0),
+            "size": code.code.len(),
+        }),
+        LedgerEntryData::Account(account) => json!({
+            "type": "account",
+            "account_id": account_strkey(&account.account_id),
+            "balance": account.balance.to_string(),
+            "seq_num": account.seq_num.0.to_string(),
+        }),
+        LedgerEntryData::Ttl(ttl) => json!({
+            "type": "ttl",
+            "key_hash": hex::encode(ttl.key_hash.0),
+            "live_until_ledger_seq": ttl.live_until_ledger_seq,
+        }),
+        other => json!({ "type": other.name() }),
+    };
+    json!({
+        "last_modified_ledger_seq": entry.last_modified_ledger_seq,
+        "data": data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_env_host::xdr::{LedgerEntryChanges, VecM};
+    use soroban_env_host::xdr::{
+        ContractDataEntry, ContractId, ExtensionPoint, Hash, InvokeContractArgs,
+        InvokeHostFunctionOp, LedgerEntryExt, LedgerKeyContractData, Limits, Memo, MuxedAccount,
+        Preconditions, ScAddress, ScSymbol, SequenceNumber, SorobanTransactionMeta,
+        SorobanTransactionMetaExt, Transaction, TransactionExt, TransactionMeta,
+        TransactionMetaV3, TransactionV1Envelope, Uint256, WriteXdr,
+    };
+
+    fn encode(value: &impl WriteXdr) -> String {
+        base64::engine::general_purpose::STANDARD.encode(value.to_xdr(Limits::none()).unwrap())
+    }
+
+    fn contract() -> ScAddress {
+        ScAddress::Contract(ContractId(Hash([2u8; 32])))
+    }
+
+    #[test]
+    fn test_decode_envelope() {
+        let envelope = TransactionEnvelope::Tx(TransactionV1Envelope {
+            tx: Transaction {
+                source_account: MuxedAccount::Ed25519(Uint256([0u8; 32])),
+                fee: 100,
+                seq_num: SequenceNumber(7),
+                cond: Preconditions::None,
+                memo: Memo::None,
+                operations: vec![Operation {
+                    source_account: None,
+                    body: OperationBody::InvokeHostFunction(InvokeHostFunctionOp {
+                        host_function: HostFunction::InvokeContract(InvokeContractArgs {
+                            contract_address: contract(),
+                            function_name: ScSymbol("hello".try_into().unwrap()),
+                            args: vec![ScVal::U32(1)].try_into().unwrap(),
+                        }),
+                        auth: VecM::default(),
+                    }),
+                }]
+                .try_into()
+                .unwrap(),
+                ext: TransactionExt::V0,
+            },
+            signatures: VecM::default(),
+        });
+
+        let json = decode_xdr("envelope", &encode(&envelope)).unwrap();
+        assert_eq!(json["type"], "tx");
+        assert_eq!(json["fee"], 100);
+        assert_eq!(json["seq_num"], "7");
+        let function = &json["operations"][0]["host_function"];
+        assert_eq!(function["function_name"], "hello");
+        assert!(function["contract"].as_str().unwrap().starts_with('C'));
+        assert_eq!(function["args"][0]["value"], 1);
+    }
+
+    #[test]
+    fn test_decode_ledger_key_and_entry() {
+        let key = LedgerKey::ContractData(LedgerKeyContractData {
+            contract: contract(),
+            key: ScVal::Symbol(ScSymbol("k".try_into().unwrap())),
+            durability: ContractDataDurability::Temporary,
+        });
+        let json = decode_xdr("ledger_key", &encode(&key)).unwrap();
+        assert_eq!(json["type"], "contract_data");
+        assert_eq!(json["key"]["value"], "k");
+        assert_eq!(json["durability"], "temporary");
+
+        let entry = LedgerEntry {
+            last_modified_ledger_seq: 9,
+            data: LedgerEntryData::ContractData(ContractDataEntry {
+                ext: ExtensionPoint::V0,
+                contract: contract(),
+                key: ScVal::U32(0),
+                durability: ContractDataDurability::Persistent,
+                val: ScVal::Bool(true),
+            }),
+            ext: LedgerEntryExt::V0,
+        };
+        let json = decode_xdr("ledger_entry", &encode(&entry)).unwrap();
+        assert_eq!(json["last_modified_ledger_seq"], 9);
+        assert_eq!(json["data"]["value"]["value"], true);
+    }
+
+    #[test]
+    fn test_decode_scval_and_meta() {
+        let json = decode_xdr("scval", &encode(&ScVal::I32(-4))).unwrap();
+        assert_eq!(json, json!({ "type": "i32", "value": -4 }));
+
+        let meta = TransactionMeta::V3(TransactionMetaV3 {
+            ext: ExtensionPoint::V0,
+            tx_changes_before: LedgerEntryChanges::default(),
+            operations: VecM::default(),
+            tx_changes_after: LedgerEntryChanges::default(),
+            soroban_meta: Some(SorobanTransactionMeta {
+                ext: SorobanTransactionMetaExt::V0,
+                events: VecM::default(),
+                return_value: ScVal::Void,
+                diagnostic_events: VecM::default(),
+            }),
+        });
+        let json = decode_xdr("meta", &encode(&meta)).unwrap();
+        assert_eq!(json["meta_version"], 3);
+        assert_eq!(json["return_value"]["type"], "void");
+    }
+
+    #[test]
+    fn test_decode_rejects_wrong_kind_and_garbage() {
+        let scval = encode(&ScVal::U32(1));
+        assert!(decode_xdr("ledger_entry", &scval)
+            .unwrap_err()
+            .starts_with("Failed to parse ledger_entry XDR"));
+        assert!(decode_xdr("scval", "%%").unwrap_err().contains("base64"));
+        assert!(decode_xdr("asset", &scval).unwrap_err().contains("Unknown XDR kind"));
+    }
+}
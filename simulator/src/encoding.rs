@@ -0,0 +1,124 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Text encodings accepted for XDR fields in a `SimulationRequest`.
+//!
+//! Stellar tooling hands out XDR as either base64 (Horizon, RPC) or hex (the
+//! Go SDK, many explorers). A request declares one encoding for all of its
+//! XDR fields; mixing the two within one request is rejected.
+
+use base64::Engine as _;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum XdrEncoding {
+    #[default]
+    Base64,
+    Hex,
+}
+
+impl XdrEncoding {
+    /// Parse the request's `encoding` field; absent means base64.
+    ///
+    /// # Errors
+    /// Names an unsupported encoding.
+    pub fn parse(name: Option<&str>) -> Result<Self, String> {
+        match name.map(str::to_ascii_lowercase).as_deref() {
+            None | Some("base64") => Ok(Self::Base64),
+            Some("hex") => Ok(Self::Hex),
+            Some(other) => Err(format!(
+                "Unsupported encoding '{other}': expected \"base64\" or \"hex\""
+            )),
+        }
+    }
+
+    /// Human-readable label used in error messages.
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Base64 => "Base64",
+            Self::Hex => "hex",
+        }
+    }
+
+    /// Decode `input` into raw XDR bytes.
+    ///
+    /// # Errors
+    /// Says why `input` is not valid in this encoding.
+    pub fn decode(self, input: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Base64 => base64::engine::general_purpose::STANDARD
+                .decode(input)
+                .map_err(|e| e.to_string()),
+            Self::Hex => hex::decode(input.trim()).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Encode raw XDR bytes as text.
+    #[must_use]
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Self::Base64 => base64::engine::general_purpose::STANDARD.encode(bytes),
+            Self::Hex => hex::encode(bytes),
+        }
+    }
+
+    /// Check that every non-empty `(field, value)` pair is plausibly in this
+    /// encoding, naming the first field that is not.
+    ///
+    /// Only hex can be checked reliably: base64 alphabets include every hex
+    /// digit, so a base64 request cannot be told apart from a short hex one.
+    ///
+    /// # Errors
+    /// Names the first field that is not in this encoding.
+    pub fn ensure_consistent<'a>(
+        self,
+        fields: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<(), String> {
+        if self != Self::Hex {
+            return Ok(());
+        }
+        for (field, value) in fields {
+            let value = value.trim();
+            if !value.is_empty() && !is_hex(value) {
+                return Err(format!(
+                    "Mixed XDR encodings: request declares hex but {field} is not hex-encoded"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_hex(s: &str) -> bool {
+    s.len().is_multiple_of(2) && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_defaults_to_base64() {
+        assert_eq!(XdrEncoding::parse(None), Ok(XdrEncoding::Base64));
+        assert_eq!(XdrEncoding::parse(Some("HEX")), Ok(XdrEncoding::Hex));
+        assert!(XdrEncoding::parse(Some("base32")).is_err());
+    }
+
+    #[test]
+    fn test_decode_hex_and_base64_agree() {
+        assert_eq!(XdrEncoding::Hex.decode("00000001").unwrap(), vec![0, 0, 0, 1]);
+        assert_eq!(XdrEncoding::Base64.decode("AAAAAQ==").unwrap(), vec![0, 0, 0, 1]);
+        assert!(XdrEncoding::Hex.decode("zz").is_err());
+    }
+
+    #[test]
+    fn test_hex_rejects_mixed_fields() {
+        let err = XdrEncoding::Hex
+            .ensure_consistent([("envelope_xdr", "00ff"), ("result_meta_xdr", "AAAAAQ==")])
+            .unwrap_err();
+        assert!(err.contains("result_meta_xdr"));
+        assert!(XdrEncoding::Hex
+            .ensure_consistent([("envelope_xdr", "00ff"), ("result_meta_xdr", "")])
+            .is_ok());
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Resource fee estimation.
+//!
+//! Feeds the resources a simulation consumed into the host's own fee
+//! formula, so the estimate matches what the network would charge for the
+//! same footprint under the given fee configuration. Rent for new or
+//! extended entries is not included.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use soroban_env_host::fees::{
+    compute_transaction_resource_fee, FeeConfiguration, TransactionResources,
+};
+
+/// Resources measured during a simulation, in the units the fee model
+/// charges for.
+#[derive(Debug, Clone, Default)]
+pub struct FeeInputs {
+    pub cpu_instructions: u64,
+    pub memory_bytes: u64,
+    pub read_entries: u32,
+    pub write_entries: u32,
+    pub read_bytes: u32,
+    pub write_bytes: u32,
+    pub contract_events_bytes: u32,
+    pub transaction_size_bytes: u32,
+}
+
+/// Caller overrides for the network fee settings, e.g. to compare against
+/// a different protocol version's configuration. Unset fields keep the
+/// defaults.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Default)]
+pub struct FeeConfigJson {
+    pub fee_per_instruction_increment: Option<i64>,
+    pub fee_per_read_entry: Option<i64>,
+    pub fee_per_write_entry: Option<i64>,
+    pub fee_per_read_1kb: Option<i64>,
+    pub fee_per_write_1kb: Option<i64>,
+    pub fee_per_historical_1kb: Option<i64>,
+    pub fee_per_contract_event_1kb: Option<i64>,
+    pub fee_per_transaction_size_1kb: Option<i64>,
+}
+
+impl FeeConfigJson {
+    /// Overwrite the fields of `config` that this override sets.
+    pub fn apply(&self, config: &mut FeeConfiguration) {
+        let overrides = [
+            (self.fee_per_instruction_increment, &mut config.fee_per_instruction_increment),
+            (self.fee_per_read_entry, &mut config.fee_per_disk_read_entry),
+            (self.fee_per_write_entry, &mut config.fee_per_write_entry),
+            (self.fee_per_read_1kb, &mut config.fee_per_disk_read_1kb),
+            (self.fee_per_write_1kb, &mut config.fee_per_write_1kb),
+            (self.fee_per_historical_1kb, &mut config.fee_per_historical_1kb),
+            (self.fee_per_contract_event_1kb, &mut config.fee_per_contract_event_1kb),
+            (self.fee_per_transaction_size_1kb, &mut config.fee_per_transaction_size_1kb),
+        ];
+        for (value, field) in overrides {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+    }
+}
+
+/// Public network fee settings as of protocol 23.
+#[must_use]
+pub const fn default_fee_config() -> FeeConfiguration {
+    FeeConfiguration {
+        fee_per_instruction_increment: 25,
+        fee_per_disk_read_entry: 6_250,
+        fee_per_write_entry: 10_000,
+        fee_per_disk_read_1kb: 1_786,
+        fee_per_write_1kb: 11_800,
+        fee_per_historical_1kb: 16_235,
+        fee_per_contract_event_1kb: 10_000,
+        fee_per_transaction_size_1kb: 1_624,
+    }
+}
+
+/// Estimated resource fee, in stroops, with the measurements it was
+/// computed from.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct FeeEstimate {
+    /// Total resource fee: `non_refundable_fee + refundable_fee`.
+    pub resource_fee: i64,
+    pub non_refundable_fee: i64,
+    /// Portion refunded if unused (contract events; rent is not estimated).
+    pub refundable_fee: i64,
+    pub cpu_instructions: u64,
+    /// Reported for reference; memory is metered but not charged.
+    pub memory_bytes: u64,
+    pub read_entries: u32,
+    pub write_entries: u32,
+    pub read_bytes: u32,
+    pub write_bytes: u32,
+    pub contract_events_bytes: u32,
+    pub transaction_size_bytes: u32,
+}
+
+/// Estimate the resource fee for `inputs` under `config`.
+#[must_use]
+pub fn estimate_fee(inputs: &FeeInputs, config: &FeeConfiguration) -> FeeEstimate {
+    let resources = TransactionResources {
+        instructions: u32::try_from(inputs.cpu_instructions).unwrap_or(u32::MAX),
+        disk_read_entries: inputs.read_entries,
+        write_entries: inputs.write_entries,
+        disk_read_bytes: inputs.read_bytes,
+        write_bytes: inputs.write_bytes,
+        contract_events_size_bytes: inputs.contract_events_bytes,
+        transaction_size_bytes: inputs.transaction_size_bytes,
+    };
+    let (non_refundable_fee, refundable_fee) = compute_transaction_resource_fee(&resources, config);
+
+    FeeEstimate {
+        resource_fee: non_refundable_fee.saturating_add(refundable_fee),
+        non_refundable_fee,
+        refundable_fee,
+        cpu_instructions: inputs.cpu_instructions,
+        memory_bytes: inputs.memory_bytes,
+        read_entries: inputs.read_entries,
+        write_entries: inputs.write_entries,
+        read_bytes: inputs.read_bytes,
+        write_bytes: inputs.write_bytes,
+        contract_events_bytes: inputs.contract_events_bytes,
+        transaction_size_bytes: inputs.transaction_size_bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs_with_cpu(cpu_instructions: u64) -> FeeInputs {
+        FeeInputs {
+            cpu_instructions,
+            read_entries: 2,
+            write_entries: 1,
+            read_bytes: 1_000,
+            write_bytes: 200,
+            transaction_size_bytes: 300,
+            ..FeeInputs::default()
+        }
+    }
+
+    #[test]
+    fn test_fee_is_monotonic_in_cpu() {
+        let config = default_fee_config();
+        let fees: Vec<i64> = [0, 10_000, 1_000_000, 100_000_000]
+            .into_iter()
+            .map(|cpu| estimate_fee(&inputs_with_cpu(cpu), &config).resource_fee)
+            .collect();
+        assert!(fees.windows(2).all(|w| w[0] < w[1]), "{fees:?}");
+    }
+
+    #[test]
+    fn test_total_is_sum_of_parts() {
+        let mut inputs = inputs_with_cpu(5_000_000);
+        inputs.contract_events_bytes = 512;
+        let estimate = estimate_fee(&inputs, &default_fee_config());
+        assert!(estimate.refundable_fee > 0);
+        assert_eq!(
+            estimate.resource_fee,
+            estimate.non_refundable_fee + estimate.refundable_fee
+        );
+    }
+
+    #[test]
+    fn test_config_override_changes_fee() {
+        let inputs = inputs_with_cpu(1_000_000);
+        let base = estimate_fee(&inputs, &default_fee_config()).resource_fee;
+
+        let mut config = default_fee_config();
+        FeeConfigJson {
+            fee_per_instruction_increment: Some(1_000),
+            ..FeeConfigJson::default()
+        }
+        .apply(&mut config);
+        assert_eq!(config.fee_per_instruction_increment, 1_000);
+        assert_eq!(config.fee_per_write_entry, 10_000);
+        assert!(estimate_fee(&inputs, &config).resource_fee > base);
+    }
+}
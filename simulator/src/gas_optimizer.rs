@@ -1,6 +1,7 @@
 // Copyright 2025 Erst Users
 // SPDX-License-Identifier: Apache-2.0
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -15,7 +16,7 @@ pub struct BudgetMetrics {
     pub total_operations: usize,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct OptimizationTip {
     pub category: String,
     pub severity: String, // "high", "medium", "low"
@@ -24,7 +25,7 @@ pub struct OptimizationTip {
     pub code_location: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct OptimizationReport {
     pub overall_efficiency: f64, // 0-100 score
     pub tips: Vec<OptimizationTip>,
@@ -39,7 +40,7 @@ pub struct GasOptimizationAdvisor {
 }
 
 impl GasOptimizationAdvisor {
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
             baseline_cpu_per_op: 1000,
             baseline_memory_per_op: 500,
@@ -47,6 +48,14 @@ impl GasOptimizationAdvisor {
     }
 
     /// Analyze budget metrics and generate optimization suggestions
+    // Budget figures stay far below 2^52, and percentages are reported to
+    // whole units, so the float casts lose nothing that matters here.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss,
+        clippy::too_many_lines
+    )]
     pub fn analyze(&self, metrics: &BudgetMetrics) -> OptimizationReport {
         let mut tips = Vec::new();
         let mut budget_breakdown = HashMap::new();
@@ -176,7 +185,7 @@ impl GasOptimizationAdvisor {
             100.0
         };
 
-        let overall_efficiency = (cpu_efficiency + memory_efficiency) / 2.0;
+        let overall_efficiency = f64::midpoint(cpu_efficiency, memory_efficiency);
 
         // Comparison summary
         let comparison = if overall_efficiency >= 90.0 {
@@ -198,7 +207,7 @@ impl GasOptimizationAdvisor {
     }
 
     /// Analyze specific operation patterns
-    #[allow(dead_code)]
+    #[allow(dead_code, clippy::unused_self)]
     pub fn analyze_operation_pattern(
         &self,
         operation_type: &str,
@@ -7,6 +7,7 @@ use std::process::Command;
 #[derive(Debug, Clone)]
 pub struct GitRepository {
     pub remote_url: String,
+    #[allow(dead_code)]
     pub branch: String,
     pub commit_hash: String,
     pub root_path: PathBuf,
@@ -19,7 +20,7 @@ impl GitRepository {
         let branch = Self::get_current_branch(&root_path).unwrap_or_else(|| "main".to_string());
         let commit_hash = Self::get_commit_hash(&root_path)?;
 
-        Some(GitRepository {
+        Some(Self {
             remote_url,
             branch,
             commit_hash,
@@ -132,7 +133,7 @@ impl GitRepository {
             path.strip_prefix(&self.root_path)
                 .ok()
                 .and_then(|p| p.to_str())
-                .map(|s| s.to_string())
+                .map(std::string::ToString::to_string)
         } else {
             Some(file_path.to_string())
         }
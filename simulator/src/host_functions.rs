@@ -0,0 +1,149 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Protocol versions in which host functions became available.
+//!
+//! A contract importing a host function that the ledger's protocol does not
+//! provide fails to link, and the host reports it as an opaque Wasm VM error.
+//! Comparing the module's imports against this table lets the simulator name
+//! the function instead.
+
+use wasmparser::{Parser, Payload};
+
+/// A host function import and the first protocol that links it.
+struct HostFunctionVersion {
+    module: &'static str,
+    export: &'static str,
+    name: &'static str,
+    min_protocol: Option<u32>,
+}
+
+/// The protocol bound of a host function, if its metadata declares one.
+macro_rules! protocol_bound {
+    () => {
+        None
+    };
+    ($protocol:literal) => {
+        Some($protocol)
+    };
+}
+
+/// Callback for `call_macro_with_all_host_functions`, which passes every
+/// host function listed in soroban-env-common's `env.json` with its import
+/// module, export name and supported protocol range.
+macro_rules! host_function_versions {
+    {
+        $(
+            $(#[$mod_attr:meta])*
+            mod $mod_id:ident $mod_str:literal
+            {
+                $(
+                    $(#[$fn_attr:meta])*
+                    { $fn_str:literal, $($min_proto:literal)?, $($max_proto:literal)?, fn $fn_id:ident $args:tt -> $ret:ty }
+                )*
+            }
+        )*
+    } => {
+        /// Every host function of the linked host, so the table always
+        /// covers the protocols that host supports.
+        static HOST_FUNCTIONS: &[HostFunctionVersion] = &[
+            $($(
+                HostFunctionVersion {
+                    module: $mod_str,
+                    export: $fn_str,
+                    name: stringify!($fn_id),
+                    min_protocol: protocol_bound!($($min_proto)?),
+                },
+            )*)*
+        ];
+    };
+}
+
+soroban_env_host::call_macro_with_all_host_functions! { host_function_versions }
+
+/// A host function `wasm` imports that `protocol` does not provide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedImport {
+    pub name: &'static str,
+    pub min_protocol: u32,
+}
+
+impl UnsupportedImport {
+    /// The message reported in place of the host's linker error.
+    pub fn message(&self, protocol: u32) -> String {
+        format!(
+            "protocol version {protocol} does not support function {} (available from protocol {})",
+            self.name, self.min_protocol
+        )
+    }
+}
+
+/// Host functions imported by `wasm` that are newer than `protocol`, in
+/// import order. Modules that fail to parse yield no results.
+pub fn unsupported_imports(wasm: &[u8], protocol: u32) -> Vec<UnsupportedImport> {
+    let mut unsupported = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        let Ok(Payload::ImportSection(reader)) = payload else {
+            continue;
+        };
+        for import in reader.into_iter().flatten() {
+            let min_protocol = HOST_FUNCTIONS
+                .iter()
+                .find(|f| (f.module, f.export) == (import.module, import.name))
+                .and_then(|f| Some((f.name, f.min_protocol?)));
+            if let Some((name, min_protocol)) = min_protocol.filter(|(_, min)| *min > protocol) {
+                unsupported.push(UnsupportedImport { name, min_protocol });
+            }
+        }
+    }
+    unsupported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEWER_IMPORTS_WAT: &str = r#"
+        (module
+            (import "x" "4" (func (result i64)))
+            (import "c" "4" (func (param i64) (result i64)))
+            (import "c" "m" (func (param i64 i64) (result i64))))
+    "#;
+
+    #[test]
+    fn test_newer_import_reported_for_older_protocol() {
+        let wasm = wat::parse_str(NEWER_IMPORTS_WAT).unwrap();
+        let unsupported = unsupported_imports(&wasm, 21);
+        assert_eq!(
+            unsupported,
+            vec![
+                UnsupportedImport {
+                    name: "bls12_381_check_g1_is_in_subgroup",
+                    min_protocol: 22,
+                },
+                UnsupportedImport {
+                    name: "bn254_g1_add",
+                    min_protocol: 25,
+                },
+            ]
+        );
+        assert_eq!(
+            unsupported_imports(&wasm, 24),
+            vec![UnsupportedImport {
+                name: "bn254_g1_add",
+                min_protocol: 25,
+            }]
+        );
+        assert_eq!(
+            unsupported[0].message(21),
+            "protocol version 21 does not support function bls12_381_check_g1_is_in_subgroup (available from protocol 22)"
+        );
+    }
+
+    #[test]
+    fn test_supported_imports_pass() {
+        let wasm = wat::parse_str(NEWER_IMPORTS_WAT).unwrap();
+        assert!(unsupported_imports(&wasm, 25).is_empty());
+        assert!(unsupported_imports(b"not wasm", 21).is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Comparison of a fresh simulation against the recorded transaction meta.
+//!
+//! In `diff` mode the simulator re-runs a transaction and reports every way
+//! the outcome departs from what the network recorded, which surfaces host
+//! behaviour that changed between versions. Ledger entries are compared by
+//! their data only: the host does not stamp `last_modified_ledger_seq` the
+//! way the network does, so that field always differs.
+
+use crate::types::{
+    ContractEventJson, MetaDifference, RecordedMeta, SimulationResponse, StateChange,
+};
+use base64::Engine as _;
+use serde_json::{json, Value};
+use soroban_env_host::xdr::{LedgerEntry, ReadXdr};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Every mismatch between `recorded` and the simulated `response`, return
+/// value first, then events in order, then state changes by ledger key.
+pub fn meta_differences(
+    recorded: &RecordedMeta,
+    response: &SimulationResponse,
+) -> Vec<MetaDifference> {
+    let mut differences = Vec::new();
+
+    if recorded.return_value_xdr != response.return_value_xdr {
+        differences.push(MetaDifference {
+            field: "return_value".to_string(),
+            recorded: json!(recorded.return_value),
+            simulated: json!(response.return_value),
+        });
+    }
+
+    let simulated_events: Vec<&ContractEventJson> = response
+        .contract_events
+        .iter()
+        .filter(|e| e.in_successful_contract_call)
+        .collect();
+    let event_count = recorded.events.len().max(simulated_events.len());
+    for index in 0..event_count {
+        let recorded_event = recorded.events.get(index);
+        let simulated_event = simulated_events.get(index).copied();
+        if recorded_event.map(|e| &e.xdr) != simulated_event.map(|e| &e.xdr) {
+            differences.push(MetaDifference {
+                field: format!("events[{index}]"),
+                recorded: json!(recorded_event),
+                simulated: json!(simulated_event),
+            });
+        }
+    }
+
+    let recorded_changes = changes_by_key(&recorded.state_changes);
+    let simulated_changes = changes_by_key(&response.state_changes);
+    let keys: BTreeSet<&str> = recorded_changes
+        .keys()
+        .chain(simulated_changes.keys())
+        .copied()
+        .collect();
+    for key in keys {
+        let recorded_change = recorded_changes.get(key).copied();
+        let simulated_change = simulated_changes.get(key).copied();
+        if !same_change(recorded_change, simulated_change) {
+            differences.push(MetaDifference {
+                field: format!("state_changes[{key}]"),
+                recorded: change_json(recorded_change),
+                simulated: change_json(simulated_change),
+            });
+        }
+    }
+
+    differences
+}
+
+fn changes_by_key(changes: &[StateChange]) -> BTreeMap<&str, &StateChange> {
+    changes.iter().map(|c| (c.key.as_str(), c)).collect()
+}
+
+fn same_change(recorded: Option<&StateChange>, simulated: Option<&StateChange>) -> bool {
+    match (recorded, simulated) {
+        (Some(r), Some(s)) => {
+            same_entry(r.before.as_deref(), s.before.as_deref())
+                && same_entry(r.after.as_deref(), s.after.as_deref())
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Whether two base64 entries hold the same data. Entries that fail to
+/// decode are compared as text.
+fn same_entry(recorded: Option<&str>, simulated: Option<&str>) -> bool {
+    match (recorded, simulated) {
+        (Some(r), Some(s)) => match (entry_data(r), entry_data(s)) {
+            (Some(r_entry), Some(s_entry)) => r_entry.data == s_entry.data,
+            _ => r == s,
+        },
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn entry_data(entry_b64: &str) -> Option<LedgerEntry> {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(entry_b64).ok()?;
+    LedgerEntry::from_xdr(bytes, crate::xdr_limits::limits()).ok()
+}
+
+fn change_json(change: Option<&StateChange>) -> Value {
+    change.map_or(Value::Null, |c| json!({ "before": c.before, "after": c.after }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorded(return_value_xdr: Option<&str>, state_changes: Vec<StateChange>) -> RecordedMeta {
+        RecordedMeta {
+            meta_version: 3,
+            return_value: None,
+            return_value_xdr: return_value_xdr.map(str::to_string),
+            events: vec![],
+            state_changes,
+        }
+    }
+
+    fn change(key: &str, after: &str) -> StateChange {
+        StateChange {
+            key: key.to_string(),
+            durability: None,
+            before: None,
+            after: Some(after.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_identical_outcome_has_no_differences() {
+        let response = SimulationResponse {
+            return_value_xdr: Some("AAAAAw==".to_string()),
+            state_changes: vec![change("a2V5", "ZW50cnk=")],
+            ..SimulationResponse::default()
+        };
+        let meta = recorded(Some("AAAAAw=="), vec![change("a2V5", "ZW50cnk=")]);
+        assert!(meta_differences(&meta, &response).is_empty());
+    }
+
+    #[test]
+    fn test_missing_and_extra_changes_are_reported() {
+        let response = SimulationResponse {
+            state_changes: vec![change("b3RoZXI=", "ZW50cnk=")],
+            ..SimulationResponse::default()
+        };
+        let meta = recorded(Some("AAAAAw=="), vec![change("a2V5", "ZW50cnk=")]);
+        let fields: Vec<String> = meta_differences(&meta, &response)
+            .into_iter()
+            .map(|d| d.field)
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                "return_value",
+                "state_changes[a2V5]",
+                "state_changes[b3RoZXI=]"
+            ]
+        );
+    }
+}
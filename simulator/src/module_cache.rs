@@ -0,0 +1,152 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compiled Wasm modules reused across requests.
+//!
+//! Parsing and validating a contract is the bulk of the cost of simulating a
+//! small invocation. In server mode the same contract is often simulated many
+//! times, so the modules for the contract code in each request's snapshot are
+//! kept in the host's `ModuleCache` and handed to the next request's host. The
+//! cache holds at most a configured number of modules, evicting the least
+//! recently used.
+//!
+//! The cache belongs to the thread that enables it and to the worker threads
+//! it spawns for timeouts and batches. It is disabled until [`enable`] is
+//! called, so one-shot CLI runs are unaffected. Modules are compiled against
+//! a budget of their own, so filling the cache never counts against the
+//! request that happens to trigger it.
+
+use crate::snapshot::LedgerSnapshot;
+use sha2::{Digest, Sha256};
+use soroban_env_host::budget::{AsBudget, Budget};
+use soroban_env_host::vm::ModuleCache;
+use soroban_env_host::xdr::{Hash, LedgerEntryData, ScErrorCode, ScErrorType};
+use soroban_env_host::{CompilationContext, Error, ErrorHandler, Host, HostError, Val};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Default number of modules kept by `--module-cache-size`.
+pub const DEFAULT_MODULE_CACHE_SIZE: usize = 32;
+
+struct LruModuleCache {
+    /// Created on first use, since the cache needs a host to compile with.
+    modules: Option<ModuleCache>,
+    /// Protocol the cached modules were compiled for.
+    protocol: u32,
+    /// Cached Wasm hashes, least recently used first.
+    recency: VecDeque<Hash>,
+    capacity: usize,
+}
+
+/// A module cache, shared by a thread and the workers simulating for it.
+#[derive(Clone)]
+pub(crate) struct SharedModuleCache(Arc<Mutex<LruModuleCache>>);
+
+thread_local! {
+    static MODULE_CACHE: RefCell<Option<SharedModuleCache>> = const { RefCell::new(None) };
+}
+
+/// Keep up to `capacity` compiled modules on this thread for later requests.
+/// A capacity of zero disables the cache.
+pub fn enable(capacity: usize) {
+    let cache = (capacity > 0).then(|| {
+        SharedModuleCache(Arc::new(Mutex::new(LruModuleCache {
+            modules: None,
+            protocol: 0,
+            recency: VecDeque::new(),
+            capacity,
+        })))
+    });
+    share(cache);
+}
+
+/// This thread's cache, for a worker thread it spawns to [`share`].
+pub(crate) fn current() -> Option<SharedModuleCache> {
+    MODULE_CACHE.with(|cache| cache.borrow().clone())
+}
+
+/// Use `cache` on this thread.
+pub(crate) fn share(cache: Option<SharedModuleCache>) {
+    MODULE_CACHE.with(|current| *current.borrow_mut() = cache);
+}
+
+/// Compiles modules outside any request, with no limit on its budget.
+#[derive(Clone)]
+struct Compiler(Budget);
+
+impl Compiler {
+    fn new() -> Result<Self, HostError> {
+        crate::cost_params::budget_with_limits(u64::MAX, u64::MAX, None).map(Self)
+    }
+}
+
+impl AsBudget for Compiler {
+    fn as_budget(&self) -> &Budget {
+        &self.0
+    }
+}
+
+impl ErrorHandler for Compiler {
+    fn map_err<T, E>(&self, res: Result<T, E>) -> Result<T, HostError>
+    where
+        Error: From<E>,
+        E: Debug,
+    {
+        res.map_err(|e| HostError::from(Error::from(e)))
+    }
+
+    fn error(&self, error: Error, _msg: &str, _args: &[Val]) -> HostError {
+        HostError::from(error)
+    }
+}
+
+impl CompilationContext for Compiler {}
+
+/// Compile any contract code in `snapshot` that is not cached yet and give
+/// `host` the cache. Does nothing when the cache is disabled.
+///
+/// # Errors
+/// Fails if a module does not compile or the host rejects the cache.
+pub fn prepare(host: &Host, snapshot: &LedgerSnapshot, protocol: u32) -> Result<(), HostError> {
+    let Some(SharedModuleCache(shared)) = current() else {
+        return Ok(());
+    };
+    let mut lru = shared.lock().unwrap_or_else(PoisonError::into_inner);
+    let compiler = Compiler::new()?;
+    // Modules compiled under another protocol may not link under this one.
+    if lru.protocol != protocol {
+        lru.modules = None;
+        lru.recency.clear();
+        lru.protocol = protocol;
+    }
+    let modules = match &lru.modules {
+        Some(modules) => modules.clone(),
+        None => lru.modules.insert(ModuleCache::new(&compiler)?).clone(),
+    };
+
+    let code_entries = snapshot
+        .contract_code()
+        .map_err(|_| HostError::from((ScErrorType::Storage, ScErrorCode::InternalError)))?;
+    for entry in &code_entries {
+        let LedgerEntryData::ContractCode(code) = &entry.data else {
+            continue;
+        };
+        let hash = Hash(Sha256::digest(code.code.as_slice()).into());
+        if let Some(position) = lru.recency.iter().position(|h| *h == hash) {
+            lru.recency.remove(position);
+        } else if !modules.contains_module(&hash)? {
+            modules.parse_and_cache_module_simple(&compiler, protocol, code.code.as_slice())?;
+        }
+        lru.recency.push_back(hash);
+
+        while lru.recency.len() > lru.capacity {
+            if let Some(evicted) = lru.recency.pop_front() {
+                modules.remove_module(&evicted)?;
+            }
+        }
+    }
+
+    host.set_module_cache(modules)
+}
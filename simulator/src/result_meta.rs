@@ -0,0 +1,229 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Decoding of the `TransactionMeta` the network recorded for a transaction.
+//!
+//! Requests may carry the original `result_meta_xdr` so the outcome of a
+//! re-simulation can be compared with what actually happened on chain. This
+//! module pulls the Soroban-relevant parts out of every meta version in the
+//! same JSON shapes the simulator reports for its own run.
+
+use crate::snapshot::entry_key;
+use crate::types::{RecordedMeta, StateChange};
+use base64::Engine as _;
+use soroban_env_host::xdr::{
+    ContractEvent, InnerTransactionResultResult, LedgerEntry, LedgerEntryChange, LedgerKey,
+    Limits, OperationMeta, OperationResult, ReadXdr, TransactionMeta, TransactionResultMeta,
+    TransactionResultResult, WriteXdr,
+};
+
+/// Decode `bytes` as a `TransactionMeta`, or as the `TransactionResultMeta`
+/// that wraps one in ledger close meta.
+pub fn decode_transaction_meta(bytes: &[u8]) -> Result<TransactionMeta, String> {
+    TransactionMeta::from_xdr(bytes, crate::xdr_limits::limits()).or_else(|meta_err| {
+        TransactionResultMeta::from_xdr(bytes, crate::xdr_limits::limits())
+            .map(|result_meta| result_meta.tx_apply_processing)
+            .map_err(|_| format!("not a TransactionMeta: {meta_err}"))
+    })
+}
+
+/// The per-operation results recorded with `bytes`, in envelope order.
+/// Only a `TransactionResultMeta` carries them; a bare `TransactionMeta`,
+/// or a transaction that failed before its operations ran, gives `None`.
+pub fn recorded_operation_results(bytes: &[u8]) -> Option<Vec<OperationResult>> {
+    let result_meta = TransactionResultMeta::from_xdr(bytes, crate::xdr_limits::limits()).ok()?;
+    match result_meta.result.result.result {
+        TransactionResultResult::TxSuccess(results) | TransactionResultResult::TxFailed(results) => {
+            Some(results.to_vec())
+        }
+        TransactionResultResult::TxFeeBumpInnerSuccess(inner)
+        | TransactionResultResult::TxFeeBumpInnerFailed(inner) => match inner.result.result {
+            InnerTransactionResultResult::TxSuccess(results)
+            | InnerTransactionResultResult::TxFailed(results) => Some(results.to_vec()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Appends the state changes of every operation in `operations` to `out`.
+fn collect_changes<'a>(operations: &'a [OperationMeta], out: &mut Vec<&'a LedgerEntryChange>) {
+    for op in operations {
+        out.extend(op.changes.iter());
+    }
+}
+
+/// The return value, contract events and per-operation state changes
+/// recorded in `meta`.
+pub fn recorded_meta(meta: &TransactionMeta) -> RecordedMeta {
+    let mut events: Vec<&ContractEvent> = Vec::new();
+    let mut changes: Vec<&LedgerEntryChange> = Vec::new();
+
+    let (version, return_value) = match meta {
+        TransactionMeta::V0(operations) => {
+            collect_changes(operations, &mut changes);
+            (0, None)
+        }
+        TransactionMeta::V1(v1) => {
+            collect_changes(&v1.operations, &mut changes);
+            (1, None)
+        }
+        TransactionMeta::V2(v2) => {
+            collect_changes(&v2.operations, &mut changes);
+            (2, None)
+        }
+        TransactionMeta::V3(v3) => {
+            collect_changes(&v3.operations, &mut changes);
+            let soroban = v3.soroban_meta.as_ref();
+            if let Some(soroban) = soroban {
+                events.extend(soroban.events.iter());
+            }
+            (3, soroban.map(|s| s.return_value.clone()))
+        }
+        TransactionMeta::V4(v4) => {
+            for op in v4.operations.iter() {
+                changes.extend(op.changes.iter());
+                events.extend(op.events.iter());
+            }
+            let return_value = v4
+                .soroban_meta
+                .as_ref()
+                .and_then(|s| s.return_value.clone());
+            (4, return_value)
+        }
+    };
+
+    RecordedMeta {
+        meta_version: version,
+        return_value: return_value.as_ref().map(crate::scval_json::scval_to_json),
+        return_value_xdr: return_value.as_ref().and_then(encode),
+        events: events
+            .into_iter()
+            .enumerate()
+            .map(|(index, event)| crate::contract_event_json(index, event, false))
+            .collect(),
+        state_changes: state_changes(&changes),
+    }
+}
+
+/// Pair each `State` snapshot with the change that follows it, giving one
+/// before/after record per entry. TTL bumps are left out, as they are in
+/// the simulator's own `state_changes`.
+fn state_changes(changes: &[&LedgerEntryChange]) -> Vec<StateChange> {
+    let mut result = Vec::new();
+    let mut before: Option<&LedgerEntry> = None;
+    for change in changes {
+        let (key, after) = match change {
+            LedgerEntryChange::State(entry) => {
+                before = Some(entry);
+                continue;
+            }
+            LedgerEntryChange::Created(entry)
+            | LedgerEntryChange::Updated(entry)
+            | LedgerEntryChange::Restored(entry) => (entry_key(entry), Some(entry)),
+            LedgerEntryChange::Removed(key) => (key.clone(), None),
+        };
+        let prior = before.take();
+        if matches!(key, LedgerKey::Ttl(_)) {
+            continue;
+        }
+        let (Some(key_xdr), Ok(before_xdr), Ok(after_xdr)) = (
+            encode(&key),
+            prior.map(|e| encode(e).ok_or(())).transpose(),
+            after.map(|e| encode(e).ok_or(())).transpose(),
+        ) else {
+            continue;
+        };
+        if before_xdr == after_xdr {
+            continue;
+        }
+        result.push(StateChange {
+            key: key_xdr,
+            durability: crate::decode::key_durability(&key).map(str::to_string),
+            before: before_xdr,
+            after: after_xdr,
+        });
+    }
+    result
+}
+
+fn encode(value: &impl WriteXdr) -> Option<String> {
+    value
+        .to_xdr(Limits::none())
+        .ok()
+        .map(|bytes| base64::engine::general_purpose::STANDARD.encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_env_host::xdr::{LedgerEntryChanges, VecM};
+    use soroban_env_host::xdr::{
+        ContractDataDurability, ContractDataEntry, ContractEventBody, ContractEventType,
+        ContractEventV0, ContractId, ExtensionPoint, Hash, LedgerEntryData, LedgerEntryExt,
+        ScAddress, ScVal, SorobanTransactionMeta, SorobanTransactionMetaExt, TransactionMetaV3,
+    };
+
+    fn data_entry(val: ScVal) -> LedgerEntry {
+        LedgerEntry {
+            last_modified_ledger_seq: 1,
+            data: LedgerEntryData::ContractData(ContractDataEntry {
+                ext: ExtensionPoint::V0,
+                contract: ScAddress::Contract(ContractId(Hash([1u8; 32]))),
+                key: ScVal::U32(0),
+                durability: ContractDataDurability::Persistent,
+                val,
+            }),
+            ext: LedgerEntryExt::V0,
+        }
+    }
+
+    #[test]
+    fn test_v3_meta_yields_return_value_events_and_changes() {
+        let event = ContractEvent {
+            ext: ExtensionPoint::V0,
+            contract_id: Some(ContractId(Hash([1u8; 32]))),
+            type_: ContractEventType::Contract,
+            body: ContractEventBody::V0(ContractEventV0 {
+                topics: VecM::default(),
+                data: ScVal::U32(9),
+            }),
+        };
+        let meta = TransactionMeta::V3(TransactionMetaV3 {
+            ext: ExtensionPoint::V0,
+            tx_changes_before: LedgerEntryChanges::default(),
+            operations: vec![OperationMeta {
+                changes: vec![
+                    LedgerEntryChange::State(data_entry(ScVal::U32(1))),
+                    LedgerEntryChange::Updated(data_entry(ScVal::U32(2))),
+                ]
+                .try_into()
+                .unwrap(),
+            }]
+            .try_into()
+            .unwrap(),
+            tx_changes_after: LedgerEntryChanges::default(),
+            soroban_meta: Some(SorobanTransactionMeta {
+                ext: SorobanTransactionMetaExt::V0,
+                events: vec![event].try_into().unwrap(),
+                return_value: ScVal::Bool(true),
+                diagnostic_events: VecM::default(),
+            }),
+        });
+
+        let bytes = meta.to_xdr(Limits::none()).unwrap();
+        let recorded = recorded_meta(&decode_transaction_meta(&bytes).unwrap());
+        assert_eq!(recorded.meta_version, 3);
+        assert_eq!(recorded.return_value.unwrap()["type"], "bool");
+        assert_eq!(recorded.events.len(), 1);
+        assert_eq!(recorded.events[0].data["value"], 9);
+        assert_eq!(recorded.state_changes.len(), 1);
+        assert!(recorded.state_changes[0].before.is_some());
+        assert!(recorded.state_changes[0].after.is_some());
+    }
+
+    #[test]
+    fn test_garbage_is_rejected() {
+        assert!(decode_transaction_meta(&[0xff; 7]).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Ledger entries fetched from a Soroban RPC server.
+//!
+//! With `rpc_url` set, the keys a simulation reports in `missing_entries`
+//! and `probed_entries` are looked up with the server's `getLedgerEntries` method instead of
+//! having to be supplied up front. Only built with the `rpc` feature.
+
+use base64::Engine as _;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use soroban_env_host::xdr::{
+    Hash, LedgerEntry, LedgerEntryData, LedgerEntryExt, LedgerKey, LedgerKeyTtl, Limits, ReadXdr,
+    TtlEntry, WriteXdr,
+};
+
+/// Most keys a single `getLedgerEntries` call may ask for.
+const MAX_KEYS_PER_CALL: usize = 200;
+
+#[derive(Deserialize)]
+struct JsonRpcResponse {
+    result: Option<LedgerEntriesResult>,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct LedgerEntriesResult {
+    #[serde(default)]
+    entries: Vec<RpcLedgerEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcLedgerEntry {
+    key: String,
+    /// Base64 `LedgerEntryData`.
+    xdr: String,
+    last_modified_ledger_seq: u32,
+    live_until_ledger_seq: Option<u32>,
+}
+
+/// Fetch the entries for the base64 `keys` from the server at `url`, as
+/// raw XDR `(LedgerKey, LedgerEntry)` pairs. Keys the server does not know
+/// are left out; contract data and code come with the TTL entry that keeps
+/// them live.
+pub fn get_ledger_entries(url: &str, keys: &[String]) -> Result<Vec<(Vec<u8>, Vec<u8>)>, String> {
+    let client = reqwest::blocking::Client::new();
+    let mut pairs = Vec::new();
+    for chunk in keys.chunks(MAX_KEYS_PER_CALL) {
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getLedgerEntries",
+            "params": { "keys": chunk },
+        });
+        let response: JsonRpcResponse = client
+            .post(url)
+            .json(&call)
+            .send()
+            .and_then(reqwest::blocking::Response::error_for_status)
+            .map_err(|e| format!("getLedgerEntries request failed: {e}"))?
+            .json()
+            .map_err(|e| format!("getLedgerEntries returned an invalid response: {e}"))?;
+        if let Some(error) = response.error {
+            return Err(format!(
+                "getLedgerEntries failed with code {}: {}",
+                error.code, error.message
+            ));
+        }
+        for entry in response.result.map(|r| r.entries).unwrap_or_default() {
+            pairs.extend(entry_pairs(&entry)?);
+        }
+    }
+    Ok(pairs)
+}
+
+/// The entry the server returned, followed by its TTL entry if it has one.
+fn entry_pairs(entry: &RpcLedgerEntry) -> Result<Vec<(Vec<u8>, Vec<u8>)>, String> {
+    let decode = |field: &str, value: &str| {
+        base64::engine::general_purpose::STANDARD
+            .decode(value)
+            .map_err(|e| format!("getLedgerEntries returned invalid base64 in {field}: {e}"))
+    };
+    let key_bytes = decode("key", &entry.key)?;
+    LedgerKey::from_xdr(&key_bytes, crate::xdr_limits::limits())
+        .map_err(|e| format!("getLedgerEntries returned an invalid key: {e}"))?;
+    let data = LedgerEntryData::from_xdr(decode("xdr", &entry.xdr)?, crate::xdr_limits::limits())
+        .map_err(|e| format!("getLedgerEntries returned an invalid entry: {e}"))?;
+    let encode = |entry: LedgerEntry| {
+        entry
+            .to_xdr(Limits::none())
+            .map_err(|e| format!("Failed to encode fetched entry: {e}"))
+    };
+
+    let mut pairs = vec![(
+        key_bytes.clone(),
+        encode(LedgerEntry {
+            last_modified_ledger_seq: entry.last_modified_ledger_seq,
+            data,
+            ext: LedgerEntryExt::V0,
+        })?,
+    )];
+    if let Some(live_until_ledger_seq) = entry.live_until_ledger_seq {
+        let key_hash = Hash(Sha256::digest(&key_bytes).into());
+        let ttl_key = LedgerKey::Ttl(LedgerKeyTtl {
+            key_hash: key_hash.clone(),
+        });
+        let ttl_entry = LedgerEntry {
+            last_modified_ledger_seq: entry.last_modified_ledger_seq,
+            data: LedgerEntryData::Ttl(TtlEntry {
+                key_hash,
+                live_until_ledger_seq,
+            }),
+            ext: LedgerEntryExt::V0,
+        };
+        let ttl_key_bytes = ttl_key
+            .to_xdr(Limits::none())
+            .map_err(|e| format!("Failed to encode TTL key: {e}"))?;
+        pairs.push((ttl_key_bytes, encode(ttl_entry)?));
+    }
+    Ok(pairs)
+}
@@ -1,13 +1,46 @@
 // Copyright 2025 Erst Users
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::gas_optimizer::{CPU_LIMIT, MEMORY_LIMIT};
+use sha2::{Digest, Sha256};
 use soroban_env_host::{
     budget::Budget,
     storage::Storage,
     xdr::{Hash, ScErrorCode, ScErrorType},
-    DiagnosticLevel, Error as EnvError, Host, HostError, TryIntoVal, Val,
+    DiagnosticLevel, Error as EnvError, Host, HostError, LedgerInfo, TryIntoVal, Val,
 };
 
+/// Passphrase of the public Stellar network.
+pub const PUBLIC_NETWORK_PASSPHRASE: &str = "Public Global Stellar Network ; September 2015";
+
+/// Passphrase of the Stellar test network.
+#[allow(dead_code)]
+pub const TESTNET_NETWORK_PASSPHRASE: &str = "Test SDF Network ; September 2015";
+
+/// Passphrase used to derive the network ID when none is configured.
+pub const DEFAULT_NETWORK_PASSPHRASE: &str = PUBLIC_NETWORK_PASSPHRASE;
+
+/// Network ID for a passphrase: the SHA-256 of its bytes.
+pub fn network_id(passphrase: &str) -> [u8; 32] {
+    Sha256::digest(passphrase.as_bytes()).into()
+}
+
+/// Ledger info installed on every new host. Contract creation and auth
+/// hashing need a network ID, and storage TTL checks need a sequence number,
+/// so the host cannot run without one.
+pub fn default_ledger_info() -> LedgerInfo {
+    LedgerInfo {
+        protocol_version: soroban_env_host::meta::INTERFACE_VERSION.protocol,
+        sequence_number: 0,
+        timestamp: 0,
+        network_id: network_id(DEFAULT_NETWORK_PASSPHRASE),
+        base_reserve: 0,
+        min_temp_entry_ttl: 16,
+        min_persistent_entry_ttl: 4096,
+        max_entry_ttl: 6_312_000,
+    }
+}
+
 #[allow(dead_code)]
 /// Wrapper around the Soroban Host to manage initialization and execution context.
 pub struct SimHost {
@@ -24,47 +57,65 @@ impl SimHost {
         budget_limits: Option<(u64, u64)>,
         calibration: Option<crate::types::ResourceCalibration>,
         memory_limit: Option<u64>,
-    ) -> Self {
-        let budget = Budget::default();
+    ) -> Result<Self, HostError> {
+        Self::with_storage(Storage::default(), budget_limits, calibration, memory_limit)
+    }
 
-        if let Some(_calib) = calibration {
-            // Resource calibration hooks are currently best-effort. Newer
-            // soroban-env-host versions no longer expose the previous model API.
-            // We keep the request field for forward compatibility.
-        }
+    /// Initialize a new Host backed by the given storage, e.g. a recording
+    /// storage over a ledger snapshot supplied with the request.
+    pub fn with_storage(
+        storage: Storage,
+        budget_limits: Option<(u64, u64)>,
+        calibration: Option<crate::types::ResourceCalibration>,
+        memory_limit: Option<u64>,
+    ) -> Result<Self, HostError> {
+        let budget = match (budget_limits, calibration) {
+            (None, None) => Budget::default(),
+            (limits, calibration) => {
+                let (cpu, mem) = limits.unwrap_or((CPU_LIMIT, MEMORY_LIMIT));
+                crate::cost_params::budget_with_limits(cpu, mem, calibration.as_ref())?
+            }
+        };
 
-        if let Some((_cpu, _mem)) = budget_limits {
-            // Budget customization requires testutils feature or extended API
-            // Using default mainnet budget settings
-        }
+        Self::with_storage_and_budget(storage, budget, memory_limit)
+    }
 
+    /// Initialize a new Host backed by the given storage that charges
+    /// against `budget`, e.g. one with an overridden cost model.
+    pub fn with_storage_and_budget(
+        storage: Storage,
+        budget: Budget,
+        memory_limit: Option<u64>,
+    ) -> Result<Self, HostError> {
         // Host::with_storage_and_budget is available in recent versions
-        let host = Host::with_storage_and_budget(Storage::default(), budget);
+        let host = Host::with_storage_and_budget(storage, budget);
 
         // Enable debug mode for better diagnostics
-        host.set_diagnostic_level(DiagnosticLevel::Debug)
-            .expect("failed to set diagnostic level");
+        host.set_diagnostic_level(DiagnosticLevel::Debug)?;
+        host.set_ledger_info(default_ledger_info())?;
 
-        Self {
+        Ok(Self {
             inner: host,
             contract_id: None,
             fn_name: None,
-            memory_limit: _memory_limit,
-        }
+            memory_limit,
+        })
     }
 
     /// Set the contract ID for execution context.
-    pub fn set_contract_id(&mut self, id: Hash) {
+    pub const fn set_contract_id(&mut self, id: Hash) {
         self.contract_id = Some(id);
     }
 
     /// Set the function name to invoke.
+    #[allow(clippy::unnecessary_wraps)]
     pub fn set_fn_name(&mut self, name: &str) -> Result<(), HostError> {
         self.fn_name = Some(name.to_string());
         Ok(())
     }
 
     /// Helper to convert a u32 to a Soroban Val
+    #[allow(clippy::unused_self)]
     pub fn val_from_u32(&self, v: u32) -> Val {
         Val::from_u32(v).into()
     }
@@ -81,12 +132,10 @@ impl SimHost {
     pub fn check_memory_limit(&self) {
         if let Some(limit) = self.memory_limit {
             if let Ok(mem_bytes) = self.inner.budget_cloned().get_mem_bytes_consumed() {
-                if mem_bytes > limit {
-                    panic!(
-                        "Memory limit exceeded: {} bytes > {} bytes limit",
-                        mem_bytes, limit
-                    );
-                }
+                assert!(
+                    mem_bytes <= limit,
+                    "Memory limit exceeded: {mem_bytes} bytes > {limit} bytes limit"
+                );
             }
         }
     }
@@ -105,6 +154,7 @@ impl SimHost {
 
         let fresh_host = Host::with_storage_and_budget(Storage::default(), budget);
         fresh_host.set_diagnostic_level(DiagnosticLevel::Debug)?;
+        fresh_host.set_ledger_info(default_ledger_info())?;
 
         if let Some(cache) = module_cache {
             fresh_host.set_module_cache(cache)?;
@@ -124,14 +174,14 @@ mod tests {
 
     #[test]
     fn test_host_initialization() {
-        let host = SimHost::new(None, None, None);
+        let host = SimHost::new(None, None, None).expect("host should initialize");
         // Basic assertion that host is functional
         assert!(host.inner.budget_cloned().get_cpu_insns_consumed().is_ok());
     }
 
     #[test]
     fn test_configuration() {
-        let mut host = SimHost::new(None, None, None);
+        let mut host = SimHost::new(None, None, None).expect("host should initialize");
         // Test setting contract ID (dummy hash)
         let hash = Hash([0u8; 32]);
         host.set_contract_id(hash);
@@ -145,7 +195,7 @@ mod tests {
 
     #[test]
     fn test_simple_value_handling() {
-        let host = SimHost::new(None, None, None);
+        let host = SimHost::new(None, None, None).expect("host should initialize");
 
         let a = 10u32;
         let b = 20u32;
@@ -163,7 +213,7 @@ mod tests {
 
     #[test]
     fn test_wipe_ledger_state_preserving_modules_without_cache() {
-        let mut host = SimHost::new(None, None, None);
+        let mut host = SimHost::new(None, None, None).expect("host should initialize");
         let before = format!("{:?}", host.inner);
 
         host.wipe_ledger_state_preserving_modules()
@@ -175,7 +225,7 @@ mod tests {
 
     #[test]
     fn test_wipe_ledger_state_preserving_modules_keeps_module_cache() {
-        let mut host = SimHost::new(None, None, None);
+        let mut host = SimHost::new(None, None, None).expect("host should initialize");
 
         let cache = ModuleCache::new(&host.inner).expect("module cache should initialize");
         host.inner
@@ -0,0 +1,83 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! JSON Schema for the request and response formats.
+//!
+//! Generated from [`SimulationRequest`], [`DirectInvokeRequest`] and
+//! [`SimulationResponse`] rather than maintained by hand, so it always
+//! matches what the simulator accepts and produces.
+
+use crate::types::{DirectInvokeRequest, SimulationRequest, SimulationResponse};
+use schemars::generate::SchemaSettings;
+use serde_json::{json, Value};
+
+/// A draft 2020-12 schema whose `$defs` describe the requests and the
+/// response. The document itself matches any of them.
+#[must_use]
+pub fn ipc_schema() -> Value {
+    // Requests are described as the simulator deserializes them and
+    // responses as it serializes them, so optional fields come out right
+    // on both sides.
+    let mut request_generator = SchemaSettings::draft2020_12()
+        .for_deserialize()
+        .into_generator();
+    let request = request_generator.subschema_for::<SimulationRequest>();
+    let direct_invoke = request_generator.subschema_for::<DirectInvokeRequest>();
+    let mut response_generator = SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator();
+    let response = response_generator.subschema_for::<SimulationResponse>();
+
+    let mut defs = request_generator.definitions().clone();
+    defs.extend(response_generator.definitions().clone());
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Simulator request and response",
+        "anyOf": [request, direct_invoke, response],
+        "$defs": defs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_documents_request_options() {
+        let schema = ipc_schema();
+        let request = &schema["$defs"]["SimulationRequest"];
+        for field in ["encoding", "ledger_info", "network_passphrase", "contract_data"] {
+            assert!(request["properties"].get(field).is_some(), "missing {field}");
+        }
+        let required = request["required"].as_array().unwrap();
+        assert!(required.contains(&json!("envelope_xdr")));
+        assert!(!required.contains(&json!("encoding")));
+        assert!(schema["$defs"].get("LedgerInfoJson").is_some());
+    }
+
+    #[test]
+    fn test_schema_documents_direct_invoke_requests() {
+        let schema = ipc_schema();
+        let request = &schema["$defs"]["DirectInvokeRequest"];
+        let required = request["required"].as_array().unwrap();
+        assert!(required.contains(&json!("contract_id")));
+        assert!(required.contains(&json!("function")));
+        assert!(!required.contains(&json!("args")));
+        assert!(schema["anyOf"]
+            .as_array()
+            .unwrap()
+            .contains(&json!({ "$ref": "#/$defs/DirectInvokeRequest" })));
+    }
+
+    #[test]
+    fn test_schema_enumerates_error_codes() {
+        let schema = ipc_schema();
+        let codes = schema["$defs"]["ErrorCode"]["enum"].as_array().unwrap();
+        assert!(codes.contains(&json!("ENVELOPE_DECODE")));
+        assert!(codes.contains(&json!("ERR_MEMORY_LIMIT_EXCEEDED")));
+        assert!(schema["$defs"]["SimulationResponse"]["properties"]
+            .get("error_code")
+            .is_some());
+    }
+}
@@ -0,0 +1,473 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Conversion of Soroban `ScVal`s into JSON.
+//!
+//! Every value is rendered as an object tagged with its `type` so that
+//! consumers can tell e.g. a `u32` from an `i64` without the XDR schema.
+//! 64-bit and wider integers (up to 256 bits) are emitted as decimal
+//! strings because JSON numbers lose precision past 2^53.
+//!
+//! [`scval_from_json`] reads the same tagged form back, for callers that
+//! describe contract values in JSON instead of XDR.
+
+use crate::strkey::{parse_sc_address, sc_address_strkey};
+use serde_json::{json, Value};
+use soroban_env_host::xdr::{
+    ContractExecutable, Duration, Int128Parts, Int256Parts, ScBytes, ScMap, ScMapEntry, ScString,
+    ScSymbol, ScVal, ScVec, TimePoint, UInt128Parts, UInt256Parts,
+};
+use std::str::FromStr;
+
+/// JSON form of a single `ScVal`.
+pub type ScValJson = Value;
+
+/// Convert an `ScVal` into its tagged JSON representation.
+#[must_use]
+pub fn scval_to_json(val: &ScVal) -> ScValJson {
+    match val {
+        ScVal::Bool(b) => json!({ "type": "bool", "value": b }),
+        ScVal::Void => json!({ "type": "void" }),
+        ScVal::Error(e) => json!({ "type": "error", "value": format!("{e:?}") }),
+        ScVal::U32(v) => json!({ "type": "u32", "value": v }),
+        ScVal::I32(v) => json!({ "type": "i32", "value": v }),
+        ScVal::U64(v) => json!({ "type": "u64", "value": v.to_string() }),
+        ScVal::I64(v) => json!({ "type": "i64", "value": v.to_string() }),
+        ScVal::Timepoint(t) => json!({ "type": "timepoint", "value": t.0.to_string() }),
+        ScVal::Duration(d) => json!({ "type": "duration", "value": d.0.to_string() }),
+        ScVal::U128(parts) => {
+            let v = (u128::from(parts.hi) << 64) | u128::from(parts.lo);
+            json!({ "type": "u128", "value": v.to_string() })
+        }
+        ScVal::I128(parts) => {
+            let v = (i128::from(parts.hi) << 64) | i128::from(parts.lo);
+            json!({ "type": "i128", "value": v.to_string() })
+        }
+        ScVal::U256(parts) => json!({
+            "type": "u256",
+            "value": u256_to_decimal([parts.hi_hi, parts.hi_lo, parts.lo_hi, parts.lo_lo]),
+        }),
+        ScVal::I256(parts) => json!({
+            "type": "i256",
+            "value": i256_to_decimal([parts.hi_hi.cast_unsigned(), parts.hi_lo, parts.lo_hi, parts.lo_lo]),
+        }),
+        ScVal::Bytes(b) => json!({ "type": "bytes", "value": hex::encode(b.as_slice()) }),
+        ScVal::String(s) => json!({ "type": "string", "value": s.to_utf8_string_lossy() }),
+        ScVal::Symbol(s) => json!({ "type": "symbol", "value": s.to_utf8_string_lossy() }),
+        ScVal::Vec(items) => {
+            let items: Vec<Value> = items
+                .as_ref()
+                .map(|v| v.iter().map(scval_to_json).collect())
+                .unwrap_or_default();
+            json!({ "type": "vec", "value": items })
+        }
+        ScVal::Map(entries) => {
+            let entries: Vec<Value> = entries
+                .as_ref()
+                .map(|m| {
+                    m.iter()
+                        .map(|e| json!({ "key": scval_to_json(&e.key), "val": scval_to_json(&e.val) }))
+                        .collect()
+                })
+                .unwrap_or_default();
+            json!({ "type": "map", "value": entries })
+        }
+        ScVal::Address(addr) => json!({ "type": "address", "value": sc_address_strkey(addr) }),
+        ScVal::LedgerKeyContractInstance => json!({ "type": "ledger_key_contract_instance" }),
+        ScVal::LedgerKeyNonce(n) => {
+            json!({ "type": "ledger_key_nonce", "value": n.nonce.to_string() })
+        }
+        ScVal::ContractInstance(instance) => json!({
+            "type": "contract_instance",
+            "executable": executable_json(&instance.executable),
+        }),
+    }
+}
+
+/// Render a contract executable: `{"wasm": "<hex hash>"}` or
+/// `"stellar_asset"`.
+#[must_use]
+pub fn executable_json(executable: &ContractExecutable) -> Value {
+    match executable {
+        ContractExecutable::Wasm(hash) => json!({ "wasm": hex::encode(hash.0) }),
+        ContractExecutable::StellarAsset => json!("stellar_asset"),
+    }
+}
+
+/// Parse the tagged JSON form produced by [`scval_to_json`] back into an
+/// `ScVal`.
+///
+/// Integers may be given as JSON numbers or decimal strings, and 256-bit
+/// integers also as `0x`-prefixed hex. Ledger-internal types are not
+/// accepted.
+///
+/// # Errors
+/// Says which part of `json` is not a valid tagged `ScVal`.
+pub fn scval_from_json(json: &Value) -> Result<ScVal, String> {
+    let ty = json
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("ScVal JSON needs a string \"type\": {json}"))?;
+    let value = json.get("value").unwrap_or(&Value::Null);
+    let text = || {
+        value
+            .as_str()
+            .ok_or_else(|| format!("{ty} value must be a string, got {value}"))
+    };
+
+    match ty {
+        "bool" => value
+            .as_bool()
+            .map(ScVal::Bool)
+            .ok_or_else(|| format!("bool value must be true or false, got {value}")),
+        "void" => Ok(ScVal::Void),
+        "u32" => parse_int(ty, value).map(ScVal::U32),
+        "i32" => parse_int(ty, value).map(ScVal::I32),
+        "u64" => parse_int(ty, value).map(ScVal::U64),
+        "i64" => parse_int(ty, value).map(ScVal::I64),
+        "timepoint" => parse_int(ty, value).map(|v| ScVal::Timepoint(TimePoint(v))),
+        "duration" => parse_int(ty, value).map(|v| ScVal::Duration(Duration(v))),
+        "u128" => parse_int::<u128>(ty, value).map(|v| ScVal::U128(u128_parts(v))),
+        "i128" => parse_int::<i128>(ty, value).map(|v| ScVal::I128(i128_parts(v))),
+        "u256" => parse_u256(ty, &int_text(ty, value)?).map(|limbs| {
+            ScVal::U256(UInt256Parts {
+                hi_hi: limbs[0],
+                hi_lo: limbs[1],
+                lo_hi: limbs[2],
+                lo_lo: limbs[3],
+            })
+        }),
+        "i256" => parse_i256(ty, &int_text(ty, value)?).map(|limbs| {
+            ScVal::I256(Int256Parts {
+                hi_hi: limbs[0].cast_signed(),
+                hi_lo: limbs[1],
+                lo_hi: limbs[2],
+                lo_lo: limbs[3],
+            })
+        }),
+        "bytes" => {
+            let bytes = hex::decode(text()?).map_err(|e| format!("bytes value: {e}"))?;
+            Ok(ScVal::Bytes(ScBytes(
+                bytes.try_into().map_err(|_| "bytes value too long")?,
+            )))
+        }
+        "string" => Ok(ScVal::String(ScString(
+            text()?.try_into().map_err(|_| "string value too long")?,
+        ))),
+        "symbol" => Ok(ScVal::Symbol(ScSymbol(
+            text()?.try_into().map_err(|_| "symbol value too long")?,
+        ))),
+        "address" => parse_sc_address(text()?).map(ScVal::Address),
+        "vec" => {
+            let items = value
+                .as_array()
+                .ok_or_else(|| format!("vec value must be an array, got {value}"))?
+                .iter()
+                .map(scval_from_json)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(ScVal::Vec(Some(ScVec(
+                items.try_into().map_err(|_| "vec value too long")?,
+            ))))
+        }
+        "map" => {
+            let entries = value
+                .as_array()
+                .ok_or_else(|| format!("map value must be an array, got {value}"))?
+                .iter()
+                .map(|entry| {
+                    Ok(ScMapEntry {
+                        key: scval_from_json(&entry["key"])?,
+                        val: scval_from_json(&entry["val"])?,
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok(ScVal::Map(Some(ScMap(
+                entries.try_into().map_err(|_| "map value too long")?,
+            ))))
+        }
+        other => Err(format!("unsupported ScVal type '{other}'")),
+    }
+}
+
+/// Read an integer given either as a JSON number or a decimal string.
+fn parse_int<T: FromStr>(ty: &str, value: &Value) -> Result<T, String> {
+    let digits = int_text(ty, value)?;
+    digits
+        .parse()
+        .map_err(|_| format!("{ty} value '{digits}' is out of range or not an integer"))
+}
+
+/// The text of an integer given either as a JSON number or a string.
+fn int_text(ty: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        other => Err(format!("{ty} value must be a number or string, got {other}")),
+    }
+}
+
+/// The high and low 64 bits of `v`.
+#[allow(clippy::cast_possible_truncation)]
+const fn u128_parts(v: u128) -> UInt128Parts {
+    UInt128Parts {
+        hi: (v >> 64) as u64,
+        lo: v as u64,
+    }
+}
+
+/// The high and low 64 bits of `v`, two's complement.
+#[allow(clippy::cast_possible_truncation)]
+const fn i128_parts(v: i128) -> Int128Parts {
+    Int128Parts {
+        hi: (v >> 64) as i64,
+        lo: v.cast_unsigned() as u64,
+    }
+}
+
+/// Parse a 256-bit unsigned integer from decimal or `0x` hex into
+/// big-endian 64-bit limbs.
+fn parse_u256(ty: &str, text: &str) -> Result<[u64; 4], String> {
+    let limbs = text
+        .strip_prefix("0x")
+        .map_or_else(|| decimal_to_u256(text), hex_to_u256);
+    limbs.ok_or_else(|| format!("{ty} value '{text}' is out of range or not an integer"))
+}
+
+/// Parse a 256-bit signed integer, optionally negative, into
+/// two's-complement big-endian limbs.
+fn parse_i256(ty: &str, text: &str) -> Result<[u64; 4], String> {
+    let out_of_range = || format!("{ty} value '{text}' is out of range or not an integer");
+    let (negative, magnitude) = match text.strip_prefix('-') {
+        Some(magnitude) => (true, parse_u256(ty, magnitude)?),
+        None => (false, parse_u256(ty, text)?),
+    };
+    // The magnitude may reach 2^255 only for the most negative value.
+    let sign_bit = magnitude[0] >> 63 == 1;
+    match (negative, sign_bit) {
+        (false, false) => Ok(magnitude),
+        (true, false) => Ok(twos_complement(magnitude)),
+        (true, true) if magnitude == [1 << 63, 0, 0, 0] => Ok(magnitude),
+        _ => Err(out_of_range()),
+    }
+}
+
+// Each step keeps the low 64 bits of a 128-bit product in the limb.
+#[allow(clippy::cast_possible_truncation)]
+fn decimal_to_u256(digits: &str) -> Option<[u64; 4]> {
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let mut limbs = [0u64; 4];
+    for digit in digits.bytes() {
+        let mut carry = u128::from(digit - b'0');
+        for limb in limbs.iter_mut().rev() {
+            let cur = u128::from(*limb) * 10 + carry;
+            *limb = cur as u64;
+            carry = cur >> 64;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    Some(limbs)
+}
+
+fn hex_to_u256(digits: &str) -> Option<[u64; 4]> {
+    if digits.is_empty() || digits.len() > 64 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let padded = format!("{digits:0>64}");
+    let mut limbs = [0u64; 4];
+    for (limb, chunk) in limbs.iter_mut().zip(padded.as_bytes().chunks(16)) {
+        *limb = u64::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+    }
+    Some(limbs)
+}
+
+/// Render a 256-bit unsigned integer, given as big-endian 64-bit limbs, in
+/// decimal by repeated long division.
+// Quotients of a 128-bit value below 10 * 2^64 by 10 fit a limb, and
+// remainders fit a digit.
+#[allow(clippy::cast_possible_truncation)]
+fn u256_to_decimal(mut limbs: [u64; 4]) -> String {
+    if limbs == [0; 4] {
+        return "0".to_string();
+    }
+    let mut digits = Vec::new();
+    while limbs != [0; 4] {
+        let mut rem: u128 = 0;
+        for limb in &mut limbs {
+            let cur = (rem << 64) | u128::from(*limb);
+            *limb = (cur / 10) as u64;
+            rem = cur % 10;
+        }
+        digits.push(b'0' + rem as u8);
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap_or_default()
+}
+
+/// Render a two's-complement 256-bit signed integer in decimal.
+fn i256_to_decimal(limbs: [u64; 4]) -> String {
+    if limbs[0] >> 63 == 0 {
+        return u256_to_decimal(limbs);
+    }
+    format!("-{}", u256_to_decimal(twos_complement(limbs)))
+}
+
+/// Negate a 256-bit two's-complement value: invert every bit, then add one
+/// with carry from the low limb.
+fn twos_complement(limbs: [u64; 4]) -> [u64; 4] {
+    let mut negated = limbs.map(|limb| !limb);
+    for limb in negated.iter_mut().rev() {
+        let (sum, overflow) = limb.overflowing_add(1);
+        *limb = sum;
+        if !overflow {
+            break;
+        }
+    }
+    negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scalar_values() {
+        assert_eq!(
+            scval_to_json(&ScVal::U32(7)),
+            json!({ "type": "u32", "value": 7 })
+        );
+        assert_eq!(
+            scval_to_json(&ScVal::I64(-3)),
+            json!({ "type": "i64", "value": "-3" })
+        );
+        assert_eq!(scval_to_json(&ScVal::Void), json!({ "type": "void" }));
+    }
+
+    #[test]
+    fn test_i128_is_exact_decimal() {
+        let val = ScVal::I128(Int128Parts { hi: -1, lo: u64::MAX });
+        assert_eq!(scval_to_json(&val)["value"], "-1");
+
+        let val = ScVal::I128(Int128Parts { hi: 1, lo: 0 });
+        assert_eq!(scval_to_json(&val)["value"], "18446744073709551616");
+    }
+
+    #[test]
+    fn test_256_bit_values_are_decimal() {
+        let max = ScVal::U256(UInt256Parts {
+            hi_hi: u64::MAX,
+            hi_lo: u64::MAX,
+            lo_hi: u64::MAX,
+            lo_lo: u64::MAX,
+        });
+        assert_eq!(
+            scval_to_json(&max)["value"],
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        );
+
+        let minus_two = ScVal::I256(Int256Parts {
+            hi_hi: -1,
+            hi_lo: u64::MAX,
+            lo_hi: u64::MAX,
+            lo_lo: u64::MAX - 1,
+        });
+        assert_eq!(scval_to_json(&minus_two)["value"], "-2");
+
+        let big = ScVal::U256(UInt256Parts { hi_hi: 0, hi_lo: 0, lo_hi: 1, lo_lo: 0 });
+        assert_eq!(scval_to_json(&big)["value"], "18446744073709551616");
+    }
+
+    #[test]
+    fn test_256_bit_extremes_round_trip() {
+        let u256_max = ScVal::U256(UInt256Parts {
+            hi_hi: u64::MAX,
+            hi_lo: u64::MAX,
+            lo_hi: u64::MAX,
+            lo_lo: u64::MAX,
+        });
+        let i256_max = ScVal::I256(Int256Parts {
+            hi_hi: i64::MAX,
+            hi_lo: u64::MAX,
+            lo_hi: u64::MAX,
+            lo_lo: u64::MAX,
+        });
+        let i256_min = ScVal::I256(Int256Parts {
+            hi_hi: i64::MIN,
+            hi_lo: 0,
+            lo_hi: 0,
+            lo_lo: 0,
+        });
+        assert_eq!(
+            scval_to_json(&i256_max)["value"],
+            "57896044618658097711785492504343953926634992332820282019728792003956564819967"
+        );
+        assert_eq!(
+            scval_to_json(&i256_min)["value"],
+            "-57896044618658097711785492504343953926634992332820282019728792003956564819968"
+        );
+        let zero = ScVal::U256(UInt256Parts { hi_hi: 0, hi_lo: 0, lo_hi: 0, lo_lo: 0 });
+        let minus_one = ScVal::I256(Int256Parts {
+            hi_hi: -1,
+            hi_lo: u64::MAX,
+            lo_hi: u64::MAX,
+            lo_lo: u64::MAX,
+        });
+        for val in [u256_max, i256_max, i256_min.clone(), zero, minus_one] {
+            assert_eq!(scval_from_json(&scval_to_json(&val)), Ok(val));
+        }
+
+        let hex = json!({ "type": "i256", "value": format!("-0x8{}", "0".repeat(63)) });
+        assert_eq!(scval_from_json(&hex), Ok(i256_min));
+        let overflow = |ty: &str, value: &str| {
+            scval_from_json(&json!({ "type": ty, "value": value })).is_err()
+        };
+        assert!(overflow(
+            "u256",
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+        ));
+        assert!(overflow(
+            "i256",
+            "57896044618658097711785492504343953926634992332820282019728792003956564819968"
+        ));
+        assert!(overflow("u256", "-1"));
+        assert!(overflow("u256", &format!("0x1{}", "0".repeat(64))));
+    }
+
+    #[test]
+    fn test_from_json_round_trips() {
+        let values = vec![
+            ScVal::Bool(true),
+            ScVal::Void,
+            ScVal::U32(7),
+            ScVal::I64(-3),
+            ScVal::I128(Int128Parts { hi: -1, lo: 5 }),
+            ScVal::Symbol(ScSymbol("balance".try_into().unwrap())),
+            ScVal::Bytes(ScBytes(vec![0xde, 0xad].try_into().unwrap())),
+            ScVal::Vec(Some(ScVec(vec![ScVal::U32(1)].try_into().unwrap()))),
+        ];
+        for val in values {
+            assert_eq!(scval_from_json(&scval_to_json(&val)), Ok(val));
+        }
+    }
+
+    #[test]
+    fn test_from_json_accepts_numbers_for_wide_ints() {
+        let val = scval_from_json(&json!({ "type": "u64", "value": 42 })).unwrap();
+        assert_eq!(val, ScVal::U64(42));
+        assert!(scval_from_json(&json!({ "type": "u32", "value": -1 })).is_err());
+        assert!(scval_from_json(&json!({ "type": "i128", "value": [1] })).is_err());
+    }
+
+    #[test]
+    fn test_nested_vec_of_symbols() {
+        let sym = ScVal::Symbol(ScSymbol("transfer".try_into().unwrap()));
+        let val = ScVal::Vec(Some(ScVec(vec![sym].try_into().unwrap())));
+        assert_eq!(
+            scval_to_json(&val),
+            json!({ "type": "vec", "value": [{ "type": "symbol", "value": "transfer" }] })
+        );
+    }
+}
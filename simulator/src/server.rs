@@ -0,0 +1,149 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Persistent server mode.
+//!
+//! Reads newline-delimited JSON `SimulationRequest`s and writes one JSON
+//! `SimulationResponse` line per request, so callers running thousands of
+//! simulations pay the process startup cost only once. Every request is
+//! simulated on a fresh `Host`; no ledger state carries over between lines,
+//! though compiled contract modules do (see [`crate::module_cache`]).
+
+use crate::types::ErrorCode;
+use crate::{simulate_value, SimulationResponse};
+use std::io::{self, BufRead, Write};
+
+/// Serve requests from `input` until EOF, writing responses to `output`.
+///
+/// Blank lines are ignored. A line that is not a valid request produces an
+/// error response rather than terminating the loop, so there is exactly one
+/// response line per request line, carrying the request's `id`. Every
+/// request passes through `prepare` before it is simulated, and every
+/// response through `finish`, which applies the caller's output options,
+/// before it is written. Up to `module_cache_size` compiled contracts are reused
+/// between requests.
+///
+/// # Errors
+/// Fails if `input` cannot be read or `output` cannot be written.
+pub fn serve<R: BufRead, W: Write>(
+    input: R,
+    mut output: W,
+    prepare: impl Fn(serde_json::Value) -> serde_json::Value,
+    finish: impl Fn(SimulationResponse) -> SimulationResponse,
+    module_cache_size: usize,
+    quiet: bool,
+) -> io::Result<()> {
+    crate::module_cache::enable(module_cache_size);
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = finish(match serde_json::from_str::<serde_json::Value>(&line) {
+            Ok(value) => simulate_value(prepare(value)),
+            Err(e) => SimulationResponse::error(ErrorCode::InvalidJson, format!("Invalid JSON: {e}")),
+        });
+
+        let json = if quiet {
+            serde_json::to_string(&response.quiet())
+        } else {
+            serde_json::to_string(&response)
+        };
+        let json = json.unwrap_or_else(|_| {
+            "{\"status\": \"error\", \"error\": \"Internal serialization error\"}".to_string()
+        });
+        writeln!(output, "{json}")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serve_answers_each_line_independently() {
+        let input = concat!(
+            r#"{"envelope_xdr": "not base64!", "result_meta_xdr": "", "enable_optimization_advisor": false, "timestamp": ""}"#,
+            "\n",
+            "this is not json\n",
+            "\n",
+            r#"{"envelope_xdr": "", "result_meta_xdr": "", "enable_optimization_advisor": false, "timestamp": ""}"#,
+            "\n",
+        );
+
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output, |request| request, |response| response, 0, false)
+            .expect("serve should not fail");
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("each line is a JSON response"))
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0]["error"]
+            .as_str()
+            .unwrap()
+            .contains("Failed to decode Envelope Base64"));
+        assert!(lines[1]["error"].as_str().unwrap().contains("Invalid JSON"));
+        assert_eq!(lines[1]["error_code"], "INVALID_JSON");
+        assert_eq!(lines[2]["status"], "error");
+    }
+
+    #[test]
+    fn test_responses_echo_request_ids() {
+        let input = concat!(
+            r#"{"id": "first", "envelope_xdr": "not base64!", "result_meta_xdr": "", "enable_optimization_advisor": false, "timestamp": ""}"#,
+            "\n",
+            r#"{"id": 2, "mode": "no_such_mode"}"#,
+            "\n",
+            r#"{"envelope_xdr": "", "result_meta_xdr": "", "enable_optimization_advisor": false, "timestamp": ""}"#,
+            "\n",
+            r#"{"id": {"batch": 3}, "mode": "validate_wasm", "wasm_base64": "AGFzbQ=="}"#,
+            "\n",
+        );
+
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output, |request| request, |response| response, 0, false)
+            .expect("serve should not fail");
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("each line is a JSON response"))
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["id"], "first");
+        assert_eq!(lines[0]["error_code"], "ENVELOPE_DECODE");
+        assert_eq!(lines[1]["id"], 2);
+        assert_eq!(lines[1]["error_code"], "INVALID_REQUEST");
+        assert!(lines[2].get("id").is_none());
+        assert_eq!(lines[3]["id"], serde_json::json!({"batch": 3}));
+    }
+
+    #[test]
+    fn test_quiet_keeps_only_the_outcome() {
+        let input = concat!(
+            r#"{"envelope_xdr": "not base64!", "result_meta_xdr": "", "enable_optimization_advisor": false, "timestamp": ""}"#,
+            "\n",
+        );
+
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output, |request| request, |response| response, 0, true)
+            .expect("serve should not fail");
+
+        let line: serde_json::Value =
+            serde_json::from_slice(&output).expect("the line is a JSON response");
+        assert_eq!(line["status"], "error");
+        assert!(line["error"]
+            .as_str()
+            .unwrap()
+            .contains("Failed to decode Envelope Base64"));
+        for field in ["logs", "warnings", "diagnostic_events", "budget_usage"] {
+            assert!(line.get(field).is_none(), "{field} should be omitted");
+        }
+    }
+}
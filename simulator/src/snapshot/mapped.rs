@@ -0,0 +1,196 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bucket files served straight from a memory map.
+//!
+//! A bucket file of a whole ledger holds far more entries than one
+//! simulation reads. [`MappedSnapshot`] maps the file and, when it is
+//! opened, reads only as much of each record as it takes to learn the
+//! entry's key, remembering where the entry lies. An entry is decoded the
+//! first time its key is looked up and kept for later lookups.
+
+use super::SnapshotError;
+use memmap2::Mmap;
+use soroban_env_host::xdr::{
+    AccountId, BucketEntryType, ClaimableBalanceId, ConfigSettingId, ContractCodeEntryExt,
+    ContractDataDurability, Error, ExtensionPoint, Hash, LedgerEntry, LedgerEntryType, LedgerKey,
+    LedgerKeyAccount, LedgerKeyClaimableBalance, LedgerKeyConfigSetting, LedgerKeyContractCode,
+    LedgerKeyContractData, LedgerKeyData, LedgerKeyLiquidityPool, LedgerKeyOffer,
+    LedgerKeyTrustLine, LedgerKeyTtl, Limited, Limits, PoolId, ReadXdr, ScAddress, ScVal, String64,
+    TrustLineAsset, WriteXdr,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::ops::Range;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Bit set in the record mark of the last fragment of an XDR record.
+const LAST_FRAGMENT: u32 = 0x8000_0000;
+
+/// Maps the file at `path` read-only.
+pub fn map_file(path: &Path) -> Result<Mmap, SnapshotError> {
+    let io_error = |e: std::io::Error| SnapshotError::Io(format!("{}: {e}", path.display()));
+    let file = File::open(path).map_err(io_error)?;
+    // SAFETY: the map is only ever read. Like any snapshot file, it must
+    // not be modified while a simulation is using it.
+    unsafe { Mmap::map(&file) }.map_err(io_error)
+}
+
+/// The entries of a record-marked `BucketEntry` stream, decoded on demand.
+#[derive(Debug)]
+pub struct MappedSnapshot {
+    map: Mmap,
+    /// Byte range in `map` of the `LedgerEntry` XDR under each key's XDR.
+    offsets: HashMap<Vec<u8>, Range<usize>>,
+    /// Entries decoded so far, by key XDR.
+    decoded: RefCell<HashMap<Vec<u8>, Rc<LedgerEntry>>>,
+}
+
+impl MappedSnapshot {
+    /// Indexes the bucket stream in `map`. As with a stream loaded into
+    /// memory, later records replace earlier ones, dead entries remove
+    /// their key, and bucket metadata is ignored.
+    pub fn new(map: Mmap) -> Result<Self, SnapshotError> {
+        let offsets = index(&map)?;
+        Ok(Self {
+            map,
+            offsets,
+            decoded: RefCell::default(),
+        })
+    }
+
+    /// Number of entries in the file.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Key XDR of every entry in the file.
+    pub fn keys(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.offsets.keys()
+    }
+
+    /// Whether the file holds an entry under `key`.
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.offsets.contains_key(key)
+    }
+
+    /// Number of distinct entries decoded so far.
+    pub fn decoded_count(&self) -> usize {
+        self.decoded.borrow().len()
+    }
+
+    /// The entry under `key`, decoding it on first use.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Rc<LedgerEntry>>, SnapshotError> {
+        let Some(range) = self.offsets.get(key) else {
+            return Ok(None);
+        };
+        if let Some(entry) = self.decoded.borrow().get(key) {
+            return Ok(Some(Rc::clone(entry)));
+        }
+        let entry = LedgerEntry::from_xdr(&self.map[range.clone()], crate::xdr_limits::limits())
+            .map_err(|e| SnapshotError::from_xdr("LedgerEntry", &e))?;
+        let entry = Rc::new(entry);
+        self.decoded
+            .borrow_mut()
+            .insert(key.to_vec(), Rc::clone(&entry));
+        Ok(Some(entry))
+    }
+}
+
+/// Walks the records of `bytes`, mapping each live key's XDR to the range
+/// of its entry.
+fn index(bytes: &[u8]) -> Result<HashMap<Vec<u8>, Range<usize>>, SnapshotError> {
+    let truncated = || SnapshotError::XdrParse("BucketEntry: truncated record".to_string());
+    let bucket_error = |e: Error| SnapshotError::from_xdr("BucketEntry", &e);
+    let encode_key = |key: &LedgerKey| {
+        key.to_xdr(Limits::none())
+            .map_err(|e| SnapshotError::XdrEncoding(format!("Failed to encode key: {e}")))
+    };
+
+    let mut offsets = HashMap::new();
+    let mut position = 0;
+    while position < bytes.len() {
+        let mark = bytes.get(position..position + 4).ok_or_else(truncated)?;
+        let mark = u32::from_be_bytes([mark[0], mark[1], mark[2], mark[3]]);
+        if mark & LAST_FRAGMENT == 0 {
+            return Err(SnapshotError::XdrParse(
+                "BucketEntry: fragmented records are not supported".to_string(),
+            ));
+        }
+        let start = position + 4;
+        let end = start + (mark & !LAST_FRAGMENT) as usize;
+        let record = bytes.get(start..end).ok_or_else(truncated)?;
+
+        let mut reader = Limited::new(record, crate::xdr_limits::limits());
+        match BucketEntryType::read_xdr(&mut reader).map_err(bucket_error)? {
+            BucketEntryType::Liveentry | BucketEntryType::Initentry => {
+                let key = entry_key(&mut reader).map_err(bucket_error)?;
+                // The entry follows the four-byte `BucketEntryType`.
+                offsets.insert(encode_key(&key)?, start + 4..end);
+            }
+            BucketEntryType::Deadentry => {
+                let key = LedgerKey::read_xdr(&mut reader).map_err(bucket_error)?;
+                offsets.remove(&encode_key(&key)?);
+            }
+            BucketEntryType::Metaentry => {}
+        }
+        position = end;
+    }
+    Ok(offsets)
+}
+
+/// The key of the `LedgerEntry` at `reader`, read from the fields that
+/// identify it without decoding the rest of the entry: a contract's code
+/// or a data entry's value is skipped over.
+fn entry_key<R: Read>(reader: &mut Limited<R>) -> Result<LedgerKey, Error> {
+    // last_modified_ledger_seq
+    u32::read_xdr(reader)?;
+    Ok(match LedgerEntryType::read_xdr(reader)? {
+        LedgerEntryType::Account => LedgerKey::Account(LedgerKeyAccount {
+            account_id: AccountId::read_xdr(reader)?,
+        }),
+        LedgerEntryType::Trustline => LedgerKey::Trustline(LedgerKeyTrustLine {
+            account_id: AccountId::read_xdr(reader)?,
+            asset: TrustLineAsset::read_xdr(reader)?,
+        }),
+        LedgerEntryType::Offer => LedgerKey::Offer(LedgerKeyOffer {
+            seller_id: AccountId::read_xdr(reader)?,
+            offer_id: i64::read_xdr(reader)?,
+        }),
+        LedgerEntryType::Data => LedgerKey::Data(LedgerKeyData {
+            account_id: AccountId::read_xdr(reader)?,
+            data_name: String64::read_xdr(reader)?,
+        }),
+        LedgerEntryType::ClaimableBalance => {
+            LedgerKey::ClaimableBalance(LedgerKeyClaimableBalance {
+                balance_id: ClaimableBalanceId::read_xdr(reader)?,
+            })
+        }
+        LedgerEntryType::LiquidityPool => LedgerKey::LiquidityPool(LedgerKeyLiquidityPool {
+            liquidity_pool_id: PoolId::read_xdr(reader)?,
+        }),
+        LedgerEntryType::ContractData => {
+            ExtensionPoint::read_xdr(reader)?;
+            LedgerKey::ContractData(LedgerKeyContractData {
+                contract: ScAddress::read_xdr(reader)?,
+                key: ScVal::read_xdr(reader)?,
+                durability: ContractDataDurability::read_xdr(reader)?,
+            })
+        }
+        LedgerEntryType::ContractCode => {
+            ContractCodeEntryExt::read_xdr(reader)?;
+            LedgerKey::ContractCode(LedgerKeyContractCode {
+                hash: Hash::read_xdr(reader)?,
+            })
+        }
+        LedgerEntryType::ConfigSetting => LedgerKey::ConfigSetting(LedgerKeyConfigSetting {
+            config_setting_id: ConfigSettingId::read_xdr(reader)?,
+        }),
+        LedgerEntryType::Ttl => LedgerKey::Ttl(LedgerKeyTtl {
+            key_hash: Hash::read_xdr(reader)?,
+        }),
+    })
+}
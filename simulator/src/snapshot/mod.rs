@@ -11,34 +11,62 @@
 //! These utilities can be shared across different Soroban tools that need
 //! to reconstruct ledger state for simulation or analysis purposes.
 
+mod mapped;
+
+use crate::encoding::XdrEncoding;
 use base64::Engine;
-use soroban_env_host::xdr::{LedgerEntry, LedgerKey, Limits, ReadXdr, WriteXdr};
+use sha2::{Digest, Sha256};
+use soroban_env_host::storage::{EntryWithLiveUntil, SnapshotSource};
+use soroban_env_host::xdr::{
+    BucketEntry, ContractDataDurability, Frame, Hash, LedgerEntry, LedgerEntryData,
+    LedgerEntryExt, LedgerEntryType, LedgerKey, LedgerKeyAccount, LedgerKeyClaimableBalance,
+    LedgerKeyConfigSetting, LedgerKeyContractCode, LedgerKeyContractData, LedgerKeyData,
+    LedgerKeyLiquidityPool, LedgerKeyOffer, LedgerKeyTrustLine, LedgerKeyTtl, Limited, Limits,
+    ReadXdr, ScErrorCode, ScErrorType, TtlEntry, WriteXdr,
+};
+use soroban_env_host::HostError;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
 
 /// Represents a decoded ledger snapshot containing key-value pairs
 /// of ledger entries ready for loading into Host storage.
 #[derive(Debug, Clone)]
 pub struct LedgerSnapshot {
-    /// Map of ledger keys to their corresponding entries
+    /// Map of ledger keys to their corresponding entries, keyed by the
+    /// key's XDR so each host storage read is a single hash lookup however
+    /// many entries the request supplied.
     entries: HashMap<Vec<u8>, LedgerEntry>,
+    /// Mapped bucket files whose entries are decoded only when looked up,
+    /// newest last. Entries held in `entries` take precedence over them.
+    files: Vec<Rc<mapped::MappedSnapshot>>,
+    /// TTLs moved during the simulation by TTL operations, by the XDR of
+    /// the entry's key. They take precedence over the stored TTL entries.
+    moved_ttls: RefCell<HashMap<Vec<u8>, u32>>,
+    /// XDR of the last key the host looked up and found absent.
+    last_absent: RefCell<Option<Vec<u8>>>,
 }
 
 impl LedgerSnapshot {
     /// Creates a new empty ledger snapshot.
+    #[must_use]
     pub fn new() -> Self {
         Self {
             entries: HashMap::new(),
+            files: Vec::new(),
+            moved_ttls: RefCell::default(),
+            last_absent: RefCell::default(),
         }
     }
 
     /// Creates a ledger snapshot from base64-encoded XDR key-value pairs.
     ///
     /// # Arguments
-    /// * `entries` - Map of base64-encoded LedgerKey to base64-encoded LedgerEntry
+    /// * `entries` - Map of base64-encoded `LedgerKey` to base64-encoded `LedgerEntry`
     ///
-    /// # Returns
-    /// * `Ok(LedgerSnapshot)` - Successfully decoded snapshot
-    /// * `Err(SnapshotError)` - Decoding or parsing failed
+    /// # Errors
+    /// Returns a `SnapshotError` if decoding or parsing fails.
     ///
     /// # Example
     /// ```ignore
@@ -48,40 +76,185 @@ impl LedgerSnapshot {
     /// let snapshot = LedgerSnapshot::from_base64_map(&entries)?;
     /// ```
     pub fn from_base64_map(entries: &HashMap<String, String>) -> Result<Self, SnapshotError> {
-        let mut decoded_entries = HashMap::new();
-
-        for (key_xdr, entry_xdr) in entries {
-            let key = decode_ledger_key(key_xdr)?;
-            let entry = decode_ledger_entry(entry_xdr)?;
+        Self::from_encoded_map(entries, XdrEncoding::Base64)
+    }
 
-            // Use the XDR-encoded key bytes as the map key for consistency
-            let key_bytes = key
-                .to_xdr(Limits::none())
-                .map_err(|e| SnapshotError::XdrEncoding(format!("Failed to encode key: {e}")))?;
+    /// Creates a ledger snapshot from XDR key-value pairs in the given text
+    /// encoding (base64 or hex).
+    ///
+    /// # Errors
+    /// Returns the error of the first pair, by key, that fails to decode.
+    pub fn from_encoded_map(
+        entries: &HashMap<String, String>,
+        encoding: XdrEncoding,
+    ) -> Result<Self, SnapshotError> {
+        let mut decoded_entries = HashMap::new();
 
+        for (key_xdr, entry_xdr) in sorted_pairs(entries) {
+            let (key_bytes, entry) = decode_pair(key_xdr, entry_xdr, encoding)?;
             decoded_entries.insert(key_bytes, entry);
         }
 
         Ok(Self {
             entries: decoded_entries,
+            files: Vec::new(),
+            moved_ttls: RefCell::default(),
+            last_absent: RefCell::default(),
+        })
+    }
+
+    /// Like [`Self::from_encoded_map`], but skipping pairs that fail to
+    /// decode. Returns the snapshot of the valid pairs and the encoded key
+    /// and error of each skipped one, ordered by key.
+    #[must_use]
+    pub fn from_encoded_map_skipping_invalid(
+        entries: &HashMap<String, String>,
+        encoding: XdrEncoding,
+    ) -> (Self, Vec<(String, SnapshotError)>) {
+        let mut decoded_entries = HashMap::new();
+        let mut skipped = Vec::new();
+
+        for (key_xdr, entry_xdr) in sorted_pairs(entries) {
+            match decode_pair(key_xdr, entry_xdr, encoding) {
+                Ok((key_bytes, entry)) => {
+                    decoded_entries.insert(key_bytes, entry);
+                }
+                Err(e) => skipped.push((key_xdr.clone(), e)),
+            }
+        }
+
+        (
+            Self {
+                entries: decoded_entries,
+                files: Vec::new(),
+                moved_ttls: RefCell::default(),
+                last_absent: RefCell::default(),
+            },
+            skipped,
+        )
+    }
+
+    /// Loads a snapshot from a file holding either a JSON object of encoded
+    /// key/entry pairs, shaped like a request's `ledger_entries`, or a
+    /// bucket file: a stream of record-marked `BucketEntry` XDR, as written
+    /// by stellar-core.
+    ///
+    /// A bucket file is memory-mapped rather than read: loading only
+    /// indexes where each entry lies, and an entry is decoded the first
+    /// time the Host asks for its key.
+    ///
+    /// # Errors
+    /// Returns a `SnapshotError` if the file cannot be read or its contents
+    /// cannot be parsed.
+    pub fn from_file(path: &Path, encoding: XdrEncoding) -> Result<Self, SnapshotError> {
+        let map = mapped::map_file(path)?;
+        if map.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+            let entries: HashMap<String, String> = serde_json::from_slice(&map)
+                .map_err(|e| SnapshotError::Json(format!("{}: {e}", path.display())))?;
+            return Self::from_encoded_map(&entries, encoding);
+        }
+        Ok(Self {
+            entries: HashMap::new(),
+            files: vec![Rc::new(mapped::MappedSnapshot::new(map)?)],
+            moved_ttls: RefCell::default(),
+            last_absent: RefCell::default(),
+        })
+    }
+
+    /// Loads the entries of a record-marked `BucketEntry` stream. Live and
+    /// init entries are added in order, dead entries remove their key, and
+    /// bucket metadata is ignored.
+    ///
+    /// # Errors
+    /// Returns a `SnapshotError` if a record fails to decode.
+    pub fn from_bucket_stream(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let encode_key = |key: &LedgerKey| {
+            key.to_xdr(Limits::none())
+                .map_err(|e| SnapshotError::XdrEncoding(format!("Failed to encode key: {e}")))
+        };
+        let mut entries = HashMap::new();
+        let mut stream = Limited::new(bytes, crate::xdr_limits::depth_limits());
+        for record in Frame::<BucketEntry>::read_xdr_iter(&mut stream) {
+            let Frame(record) = record.map_err(|e| SnapshotError::from_xdr("BucketEntry", &e))?;
+            match record {
+                BucketEntry::Liveentry(entry) | BucketEntry::Initentry(entry) => {
+                    entries.insert(encode_key(&entry_key(&entry))?, entry);
+                }
+                BucketEntry::Deadentry(key) => {
+                    entries.remove(&encode_key(&key)?);
+                }
+                BucketEntry::Metaentry(_) => {}
+            }
+        }
+        Ok(Self {
+            entries,
+            files: Vec::new(),
+            moved_ttls: RefCell::default(),
+            last_absent: RefCell::default(),
         })
     }
 
+    /// Adds every entry of `other`, replacing entries under the same key.
+    pub fn merge(&mut self, other: Self) {
+        for file in &other.files {
+            self.entries.retain(|key, _| !file.contains(key));
+        }
+        self.files.extend(other.files);
+        self.entries.extend(other.entries);
+    }
+
     /// Returns the number of entries in the snapshot.
     pub fn len(&self) -> usize {
-        self.entries.len()
+        let mapped = self.files.iter().enumerate().map(|(i, file)| {
+            file.keys()
+                .filter(|key| {
+                    !self.entries.contains_key(*key)
+                        && !self.files[i + 1..].iter().any(|newer| newer.contains(key))
+                })
+                .count()
+        });
+        self.entries.len() + mapped.sum::<usize>()
     }
 
     /// Returns true if the snapshot contains no entries.
     #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
-        self.entries.is_empty()
+        self.len() == 0
     }
 
-    /// Returns an iterator over the entries in the snapshot.
-    #[allow(dead_code)]
-    pub fn iter(&self) -> impl Iterator<Item = (&Vec<u8>, &LedgerEntry)> {
-        self.entries.iter()
+    /// The number of entries decoded so far out of those indexed in mapped
+    /// bucket files, or `None` if the snapshot has no mapped file.
+    pub fn mapped_decoded(&self) -> Option<(usize, usize)> {
+        if self.files.is_empty() {
+            return None;
+        }
+        let decoded = self.files.iter().map(|file| file.decoded_count()).sum();
+        let indexed = self.files.iter().map(|file| file.len()).sum();
+        Some((decoded, indexed))
+    }
+
+    /// Returns the key XDR of every entry, whether held in memory or in a
+    /// mapped bucket file, ordered so that callers see the same order on
+    /// every run. Use [`Self::load`] for the entries themselves.
+    pub fn keys(&self) -> Vec<&Vec<u8>> {
+        let mapped = self.files.iter().flat_map(|file| file.keys());
+        let mut keys: Vec<_> = self.entries.keys().chain(mapped).collect();
+        keys.sort_unstable();
+        keys.dedup();
+        keys
+    }
+
+    /// Returns the contract code entries, in key order. Mapped entries of
+    /// other types are left undecoded.
+    ///
+    /// # Errors
+    /// Returns a `SnapshotError` if a mapped entry fails to decode.
+    pub fn contract_code(&self) -> Result<Vec<Rc<LedgerEntry>>, SnapshotError> {
+        self.keys()
+            .into_iter()
+            .filter(|key| is_key_type(key, LedgerEntryType::ContractCode))
+            .filter_map(|key| self.load(key).transpose())
+            .collect()
     }
 
     /// Inserts a new entry into the snapshot.
@@ -94,11 +267,188 @@ impl LedgerSnapshot {
         self.entries.insert(key, entry);
     }
 
-    /// Gets an entry from the snapshot by key.
+    /// Inserts a contract data or code entry together with the TTL entry
+    /// that keeps it live until `live_until_ledger_seq`.
+    ///
+    /// # Errors
+    /// Returns a `SnapshotError` if a key cannot be encoded.
+    pub fn insert_with_ttl(
+        &mut self,
+        key: &LedgerKey,
+        entry: LedgerEntry,
+        live_until_ledger_seq: u32,
+    ) -> Result<(), SnapshotError> {
+        let key_bytes = key
+            .to_xdr(Limits::none())
+            .map_err(|e| SnapshotError::XdrEncoding(format!("Failed to encode key: {e}")))?;
+        let key_hash = Hash(Sha256::digest(&key_bytes).into());
+        let ttl_key = LedgerKey::Ttl(LedgerKeyTtl {
+            key_hash: key_hash.clone(),
+        });
+        let ttl_entry = LedgerEntry {
+            last_modified_ledger_seq: entry.last_modified_ledger_seq,
+            data: LedgerEntryData::Ttl(TtlEntry {
+                key_hash,
+                live_until_ledger_seq,
+            }),
+            ext: LedgerEntryExt::V0,
+        };
+        let ttl_bytes = ttl_key
+            .to_xdr(Limits::none())
+            .map_err(|e| SnapshotError::XdrEncoding(format!("Failed to encode key: {e}")))?;
+
+        self.entries.insert(key_bytes, entry);
+        self.entries.insert(ttl_bytes, ttl_entry);
+        Ok(())
+    }
+
+    /// Gets an entry held in memory by key.
     #[allow(dead_code)]
     pub fn get(&self, key: &[u8]) -> Option<&LedgerEntry> {
         self.entries.get(key)
     }
+
+    /// Gets an entry by key, decoding it from a mapped bucket file if it
+    /// is not held in memory.
+    ///
+    /// # Errors
+    /// Returns a `SnapshotError` if a mapped entry fails to decode.
+    pub fn load(&self, key: &[u8]) -> Result<Option<Rc<LedgerEntry>>, SnapshotError> {
+        if let Some(entry) = self.entries.get(key) {
+            return Ok(Some(Rc::new(entry.clone())));
+        }
+        for file in self.files.iter().rev() {
+            if let Some(entry) = file.get(key)? {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the `live_until_ledger_seq` recorded for a contract data or
+    /// code key, looked up through the TTL entry keyed by the SHA-256 of the
+    /// key's XDR. Other key types have no TTL and yield `None`.
+    pub fn live_until(&self, key: &LedgerKey) -> Option<u32> {
+        if !matches!(key, LedgerKey::ContractData(_) | LedgerKey::ContractCode(_)) {
+            return None;
+        }
+        let key_bytes = key.to_xdr(Limits::none()).ok()?;
+        if let Some(moved) = self.moved_ttls.borrow().get(&key_bytes) {
+            return Some(*moved);
+        }
+        let ttl_key = LedgerKey::Ttl(LedgerKeyTtl {
+            key_hash: Hash(Sha256::digest(&key_bytes).into()),
+        });
+        let ttl_bytes = ttl_key.to_xdr(Limits::none()).ok()?;
+        match &self.load(&ttl_bytes).ok()??.data {
+            LedgerEntryData::Ttl(ttl) => Some(ttl.live_until_ledger_seq),
+            _ => None,
+        }
+    }
+
+    /// Records that the simulation moved `key`'s TTL to `live_until`.
+    /// [`Self::live_until`] returns it from then on.
+    pub fn move_live_until(&self, key: &LedgerKey, live_until: u32) {
+        if let Ok(key_bytes) = key.to_xdr(Limits::none()) {
+            self.moved_ttls.borrow_mut().insert(key_bytes, live_until);
+        }
+    }
+
+    /// XDR of the last key the host looked up that the snapshot does not
+    /// hold. When a read of a missing entry ends an invocation, this is the
+    /// key it read.
+    pub fn last_absent_lookup(&self) -> Option<Vec<u8>> {
+        self.last_absent.borrow().clone()
+    }
+
+    /// The TTL the simulation moved `key` to, if any.
+    pub fn moved_live_until(&self, key: &LedgerKey) -> Option<u32> {
+        let key_bytes = key.to_xdr(Limits::none()).ok()?;
+        self.moved_ttls.borrow().get(&key_bytes).copied()
+    }
+}
+
+/// The key identifying `entry` in the ledger.
+#[must_use]
+pub fn entry_key(entry: &LedgerEntry) -> LedgerKey {
+    match &entry.data {
+        LedgerEntryData::Account(account) => LedgerKey::Account(LedgerKeyAccount {
+            account_id: account.account_id.clone(),
+        }),
+        LedgerEntryData::Trustline(trustline) => LedgerKey::Trustline(LedgerKeyTrustLine {
+            account_id: trustline.account_id.clone(),
+            asset: trustline.asset.clone(),
+        }),
+        LedgerEntryData::Offer(offer) => LedgerKey::Offer(LedgerKeyOffer {
+            seller_id: offer.seller_id.clone(),
+            offer_id: offer.offer_id,
+        }),
+        LedgerEntryData::Data(data) => LedgerKey::Data(LedgerKeyData {
+            account_id: data.account_id.clone(),
+            data_name: data.data_name.clone(),
+        }),
+        LedgerEntryData::ClaimableBalance(balance) => {
+            LedgerKey::ClaimableBalance(LedgerKeyClaimableBalance {
+                balance_id: balance.balance_id.clone(),
+            })
+        }
+        LedgerEntryData::LiquidityPool(pool) => LedgerKey::LiquidityPool(LedgerKeyLiquidityPool {
+            liquidity_pool_id: pool.liquidity_pool_id.clone(),
+        }),
+        LedgerEntryData::ContractData(data) => LedgerKey::ContractData(LedgerKeyContractData {
+            contract: data.contract.clone(),
+            key: data.key.clone(),
+            durability: data.durability,
+        }),
+        LedgerEntryData::ContractCode(code) => LedgerKey::ContractCode(LedgerKeyContractCode {
+            hash: code.hash.clone(),
+        }),
+        LedgerEntryData::ConfigSetting(setting) => {
+            LedgerKey::ConfigSetting(LedgerKeyConfigSetting {
+                config_setting_id: setting.discriminant(),
+            })
+        }
+        LedgerEntryData::Ttl(ttl) => LedgerKey::Ttl(LedgerKeyTtl {
+            key_hash: ttl.key_hash.clone(),
+        }),
+    }
+}
+
+/// Whether `key` names a persistent entry, i.e. one that is archived rather
+/// than deleted when its TTL runs out.
+#[must_use]
+pub fn is_persistent(key: &LedgerKey) -> bool {
+    match key {
+        LedgerKey::ContractCode(_) => true,
+        LedgerKey::ContractData(data) => data.durability == ContractDataDurability::Persistent,
+        _ => false,
+    }
+}
+
+/// Whether `key` is the XDR of a ledger key of type `kind`, read from its
+/// leading discriminant without decoding the rest.
+#[must_use]
+pub fn is_key_type(key: &[u8], kind: LedgerEntryType) -> bool {
+    key.get(..4) == Some((kind as i32).to_be_bytes().as_slice())
+}
+
+/// Serves entries to the Host's recording storage. Keys absent from the
+/// snapshot are reported as missing; TTLs are forwarded so the Host can
+/// reject access to archived entries itself.
+impl SnapshotSource for LedgerSnapshot {
+    fn get(&self, key: &Rc<LedgerKey>) -> Result<Option<EntryWithLiveUntil>, HostError> {
+        let key_bytes = key
+            .to_xdr(Limits::none())
+            .map_err(|_| HostError::from((ScErrorType::Storage, ScErrorCode::InternalError)))?;
+        let entry = self
+            .load(&key_bytes)
+            .map_err(|_| HostError::from((ScErrorType::Storage, ScErrorCode::InternalError)))?;
+        let Some(entry) = entry else {
+            *self.last_absent.borrow_mut() = Some(key_bytes);
+            return Ok(None);
+        };
+        Ok(Some((entry, self.live_until(key))))
+    }
 }
 
 impl Default for LedgerSnapshot {
@@ -108,30 +458,79 @@ impl Default for LedgerSnapshot {
 }
 
 /// Errors that can occur during snapshot operations.
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug)]
 pub enum SnapshotError {
-    #[error("Failed to decode base64: {0}")]
     Base64Decode(String),
-
-    #[error("Failed to parse XDR: {0}")]
+    HexDecode(String),
     XdrParse(String),
-
-    #[error("Failed to encode XDR: {0}")]
     XdrEncoding(String),
-
-    #[error("Storage operation failed: {0}")]
+    XdrLimitExceeded(String),
+    Io(String),
+    Json(String),
     #[allow(dead_code)]
     StorageError(String),
 }
 
-/// Decodes a base64-encoded LedgerKey XDR string.
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Base64Decode(e) => write!(f, "Failed to decode base64: {e}"),
+            Self::HexDecode(e) => write!(f, "Failed to decode hex: {e}"),
+            Self::XdrParse(e) => write!(f, "Failed to parse XDR: {e}"),
+            Self::XdrEncoding(e) => write!(f, "Failed to encode XDR: {e}"),
+            Self::XdrLimitExceeded(e) => {
+                write!(f, "XDR exceeds the decoding limits: {e}")
+            }
+            Self::Io(e) => write!(f, "Failed to read snapshot file: {e}"),
+            Self::Json(e) => write!(f, "Failed to parse snapshot JSON: {e}"),
+            Self::StorageError(e) => write!(f, "Storage operation failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl SnapshotError {
+    /// Classify a failure to decode a `what` from XDR.
+    fn from_xdr(what: &str, error: &soroban_env_host::xdr::Error) -> Self {
+        if crate::xdr_limits::is_limit_error(error) {
+            Self::XdrLimitExceeded(format!("{what}: {error}"))
+        } else {
+            Self::XdrParse(format!("{what}: {error}"))
+        }
+    }
+}
+
+/// Decodes one key/entry pair, returning the key as XDR bytes (the map key
+/// used throughout the snapshot) and the entry.
+/// The pairs of `entries` ordered by encoded key, so that loading, and the
+/// first decode error it reports, do not depend on hash order.
+fn sorted_pairs(entries: &HashMap<String, String>) -> Vec<(&String, &String)> {
+    let mut pairs: Vec<_> = entries.iter().collect();
+    pairs.sort();
+    pairs
+}
+
+fn decode_pair(
+    key_xdr: &str,
+    entry_xdr: &str,
+    encoding: XdrEncoding,
+) -> Result<(Vec<u8>, LedgerEntry), SnapshotError> {
+    let key = decode_ledger_key_with(key_xdr, encoding)?;
+    let entry = decode_ledger_entry_with(entry_xdr, encoding)?;
+    let key_bytes = key
+        .to_xdr(Limits::none())
+        .map_err(|e| SnapshotError::XdrEncoding(format!("Failed to encode key: {e}")))?;
+    Ok((key_bytes, entry))
+}
+
+/// Decodes a base64-encoded `LedgerKey` XDR string.
 ///
 /// # Arguments
-/// * `key_xdr` - Base64-encoded LedgerKey
+/// * `key_xdr` - Base64-encoded `LedgerKey`
 ///
-/// # Returns
-/// * `Ok(LedgerKey)` - Successfully decoded key
-/// * `Err(SnapshotError)` - Decoding or parsing failed
+/// # Errors
+/// Returns a `SnapshotError` if decoding or parsing fails.
 pub fn decode_ledger_key(key_xdr: &str) -> Result<LedgerKey, SnapshotError> {
     if key_xdr.is_empty() {
         return Err(SnapshotError::Base64Decode(
@@ -149,18 +548,17 @@ pub fn decode_ledger_key(key_xdr: &str) -> Result<LedgerKey, SnapshotError> {
         ));
     }
 
-    LedgerKey::from_xdr(bytes, Limits::none())
-        .map_err(|e| SnapshotError::XdrParse(format!("LedgerKey: {e}")))
+    LedgerKey::from_xdr(bytes, crate::xdr_limits::limits())
+        .map_err(|e| SnapshotError::from_xdr("LedgerKey", &e))
 }
 
-/// Decodes a base64-encoded LedgerEntry XDR string.
+/// Decodes a base64-encoded `LedgerEntry` XDR string.
 ///
 /// # Arguments
-/// * `entry_xdr` - Base64-encoded LedgerEntry
+/// * `entry_xdr` - Base64-encoded `LedgerEntry`
 ///
-/// # Returns
-/// * `Ok(LedgerEntry)` - Successfully decoded entry
-/// * `Err(SnapshotError)` - Decoding or parsing failed
+/// # Errors
+/// Returns a `SnapshotError` if decoding or parsing fails.
 pub fn decode_ledger_entry(entry_xdr: &str) -> Result<LedgerEntry, SnapshotError> {
     if entry_xdr.is_empty() {
         return Err(SnapshotError::Base64Decode(
@@ -178,13 +576,52 @@ pub fn decode_ledger_entry(entry_xdr: &str) -> Result<LedgerEntry, SnapshotError
         ));
     }
 
-    LedgerEntry::from_xdr(bytes, Limits::none())
-        .map_err(|e| SnapshotError::XdrParse(format!("LedgerEntry: {e}")))
+    LedgerEntry::from_xdr(bytes, crate::xdr_limits::limits())
+        .map_err(|e| SnapshotError::from_xdr("LedgerEntry", &e))
+}
+
+/// Decodes a `LedgerKey` XDR string in the given text encoding.
+///
+/// # Errors
+/// Returns a `SnapshotError` if decoding or parsing fails.
+pub fn decode_ledger_key_with(
+    key_xdr: &str,
+    encoding: XdrEncoding,
+) -> Result<LedgerKey, SnapshotError> {
+    match encoding {
+        XdrEncoding::Base64 => decode_ledger_key(key_xdr),
+        XdrEncoding::Hex => {
+            let bytes = encoding
+                .decode(key_xdr)
+                .map_err(|e| SnapshotError::HexDecode(format!("LedgerKey: {e}")))?;
+            LedgerKey::from_xdr(bytes, crate::xdr_limits::limits())
+                .map_err(|e| SnapshotError::from_xdr("LedgerKey", &e))
+        }
+    }
+}
+
+/// Decodes a `LedgerEntry` XDR string in the given text encoding.
+///
+/// # Errors
+/// Returns a `SnapshotError` if decoding or parsing fails.
+pub fn decode_ledger_entry_with(
+    entry_xdr: &str,
+    encoding: XdrEncoding,
+) -> Result<LedgerEntry, SnapshotError> {
+    match encoding {
+        XdrEncoding::Base64 => decode_ledger_entry(entry_xdr),
+        XdrEncoding::Hex => {
+            let bytes = encoding
+                .decode(entry_xdr)
+                .map_err(|e| SnapshotError::HexDecode(format!("LedgerEntry: {e}")))?;
+            LedgerEntry::from_xdr(bytes, crate::xdr_limits::limits())
+                .map_err(|e| SnapshotError::from_xdr("LedgerEntry", &e))
+        }
+    }
 }
 
 /// Statistics about a loaded snapshot.
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct LoadStats {
     /// Number of entries successfully loaded
     pub loaded_count: usize,
@@ -196,8 +633,8 @@ pub struct LoadStats {
 
 impl LoadStats {
     /// Creates new load statistics.
-    #[allow(dead_code)]
-    pub fn new(loaded: usize, failed: usize, total: usize) -> Self {
+    #[must_use]
+    pub const fn new(loaded: usize, failed: usize, total: usize) -> Self {
         Self {
             loaded_count: loaded,
             failed_count: failed,
@@ -207,7 +644,8 @@ impl LoadStats {
 
     /// Returns true if all entries were loaded successfully.
     #[allow(dead_code)]
-    pub fn is_complete(&self) -> bool {
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
         self.failed_count == 0 && self.loaded_count == self.total_count
     }
 }
@@ -215,6 +653,7 @@ impl LoadStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use soroban_env_host::xdr::{AccountEntryExt, String32, VecM};
 
     #[test]
     fn test_snapshot_creation() {
@@ -229,7 +668,7 @@ mod tests {
         let key = vec![1, 2, 3, 4];
         let entry = create_dummy_ledger_entry();
 
-        snapshot.insert(key.clone(), entry.clone());
+        snapshot.insert(key.clone(), entry);
         assert_eq!(snapshot.len(), 1);
         assert!(!snapshot.is_empty());
         assert!(snapshot.get(&key).is_some());
@@ -279,6 +718,118 @@ mod tests {
         assert!(matches!(result.unwrap_err(), SnapshotError::Base64Decode(_)));
     }
 
+    #[test]
+    fn test_is_persistent_by_durability() {
+        use soroban_env_host::xdr::{
+            ContractId, LedgerKeyContractCode, LedgerKeyContractData, ScAddress, ScVal,
+        };
+
+        let data_key = |durability| {
+            LedgerKey::ContractData(LedgerKeyContractData {
+                contract: ScAddress::Contract(ContractId(Hash([1u8; 32]))),
+                key: ScVal::U32(1),
+                durability,
+            })
+        };
+        assert!(is_persistent(&data_key(ContractDataDurability::Persistent)));
+        assert!(!is_persistent(&data_key(ContractDataDurability::Temporary)));
+        assert!(is_persistent(&LedgerKey::ContractCode(LedgerKeyContractCode {
+            hash: Hash([2u8; 32]),
+        })));
+        assert!(!is_persistent(&LedgerKey::Ttl(LedgerKeyTtl {
+            key_hash: Hash([3u8; 32]),
+        })));
+    }
+
+    #[test]
+    fn test_snapshot_source_serves_contract_code_with_ttl() {
+        use soroban_env_host::xdr::{
+            ContractCodeEntry, ContractCodeEntryExt, LedgerKeyContractCode, TtlEntry,
+        };
+
+        let code_hash = Hash([7u8; 32]);
+        let code_key = LedgerKey::ContractCode(LedgerKeyContractCode {
+            hash: code_hash.clone(),
+        });
+        let code_entry = LedgerEntry {
+            last_modified_ledger_seq: 1,
+            data: LedgerEntryData::ContractCode(ContractCodeEntry {
+                ext: ContractCodeEntryExt::V0,
+                hash: code_hash,
+                code: b"\0asm\x01\0\0\0".to_vec().try_into().unwrap(),
+            }),
+            ext: LedgerEntryExt::default(),
+        };
+
+        let code_key_bytes = code_key.to_xdr(Limits::none()).unwrap();
+        let key_hash = Hash(Sha256::digest(&code_key_bytes).into());
+        let ttl_key = LedgerKey::Ttl(LedgerKeyTtl {
+            key_hash: key_hash.clone(),
+        });
+        let ttl_entry = LedgerEntry {
+            last_modified_ledger_seq: 1,
+            data: LedgerEntryData::Ttl(TtlEntry {
+                key_hash,
+                live_until_ledger_seq: 500,
+            }),
+            ext: LedgerEntryExt::default(),
+        };
+
+        let mut snapshot = LedgerSnapshot::new();
+        snapshot.insert(code_key_bytes, code_entry.clone());
+        snapshot.insert(ttl_key.to_xdr(Limits::none()).unwrap(), ttl_entry);
+
+        let (entry, live_until) = SnapshotSource::get(&snapshot, &Rc::new(code_key))
+            .expect("lookup should succeed")
+            .expect("code entry should be present");
+        assert_eq!(*entry, code_entry);
+        assert_eq!(live_until, Some(500));
+
+        let missing = LedgerKey::ContractCode(LedgerKeyContractCode {
+            hash: Hash([8u8; 32]),
+        });
+        assert!(SnapshotSource::get(&snapshot, &Rc::new(missing))
+            .expect("lookup should succeed")
+            .is_none());
+    }
+
+    #[test]
+    fn test_skipping_invalid_keeps_valid_pairs() {
+        let entry = create_dummy_ledger_entry();
+        let LedgerEntryData::Account(account) = &entry.data else {
+            unreachable!();
+        };
+        let key = LedgerKey::Account(soroban_env_host::xdr::LedgerKeyAccount {
+            account_id: account.account_id.clone(),
+        });
+        let encode = |bytes: Vec<u8>| base64::engine::general_purpose::STANDARD.encode(bytes);
+        let mut entries = HashMap::new();
+        entries.insert(
+            encode(key.to_xdr(Limits::none()).unwrap()),
+            encode(entry.to_xdr(Limits::none()).unwrap()),
+        );
+        entries.insert("garbage".to_string(), "AAAA".to_string());
+
+        let (snapshot, skipped) =
+            LedgerSnapshot::from_encoded_map_skipping_invalid(&entries, XdrEncoding::Base64);
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0, "garbage");
+    }
+
+    #[test]
+    fn test_decode_hex_ledger_entry() {
+        let entry = create_dummy_ledger_entry();
+        let hex_xdr = hex::encode(entry.to_xdr(Limits::none()).unwrap());
+
+        let decoded = decode_ledger_entry_with(&hex_xdr, XdrEncoding::Hex).unwrap();
+        assert_eq!(decoded, entry);
+        assert!(matches!(
+            decode_ledger_entry_with("not hex", XdrEncoding::Hex),
+            Err(SnapshotError::HexDecode(_))
+        ));
+    }
+
     #[test]
     fn test_load_stats() {
         let stats = LoadStats::new(10, 0, 10);
@@ -289,6 +840,95 @@ mod tests {
     }
 
     // Helper function to create a dummy ledger entry for testing
+    /// `records` with XDR record marking, as in a bucket file.
+    fn bucket_stream(records: &[BucketEntry]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for record in records {
+            let body = record.to_xdr(Limits::none()).unwrap();
+            let header = u32::try_from(body.len()).unwrap() | 0x8000_0000;
+            bytes.extend_from_slice(&header.to_be_bytes());
+            bytes.extend_from_slice(&body);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_bucket_stream_applies_live_and_dead_entries() {
+        use soroban_env_host::xdr::{ContractCodeEntry, ContractCodeEntryExt};
+
+        let account = create_dummy_ledger_entry();
+        let code = LedgerEntry {
+            last_modified_ledger_seq: 1,
+            data: LedgerEntryData::ContractCode(ContractCodeEntry {
+                ext: ContractCodeEntryExt::V0,
+                hash: Hash([4u8; 32]),
+                code: vec![0u8; 8].try_into().unwrap(),
+            }),
+            ext: LedgerEntryExt::V0,
+        };
+        let stream = bucket_stream(&[
+            BucketEntry::Liveentry(account.clone()),
+            BucketEntry::Initentry(code),
+            BucketEntry::Deadentry(LedgerKey::ContractCode(LedgerKeyContractCode {
+                hash: Hash([4u8; 32]),
+            })),
+        ]);
+
+        let snapshot = LedgerSnapshot::from_bucket_stream(&stream).unwrap();
+        assert_eq!(snapshot.len(), 1);
+        let account_key = entry_key(&account).to_xdr(Limits::none()).unwrap();
+        assert_eq!(snapshot.get(&account_key), Some(&account));
+    }
+
+    #[test]
+    fn test_from_file_reads_json_map() {
+        let entry = create_dummy_ledger_entry();
+        let encode = |bytes: Vec<u8>| base64::engine::general_purpose::STANDARD.encode(bytes);
+        let map = HashMap::from([(
+            encode(entry_key(&entry).to_xdr(Limits::none()).unwrap()),
+            encode(entry.to_xdr(Limits::none()).unwrap()),
+        )]);
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("snapshot.json");
+        std::fs::write(&path, serde_json::to_vec(&map).unwrap()).unwrap();
+
+        let snapshot = LedgerSnapshot::from_file(&path, XdrEncoding::Base64).unwrap();
+        assert_eq!(snapshot.len(), 1);
+        assert!(matches!(
+            LedgerSnapshot::from_file(&dir.path().join("absent"), XdrEncoding::Base64),
+            Err(SnapshotError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_keys_and_contract_code_cover_mapped_files() {
+        use soroban_env_host::xdr::{ContractCodeEntry, ContractCodeEntryExt};
+
+        let account = create_dummy_ledger_entry();
+        let code = LedgerEntry {
+            last_modified_ledger_seq: 1,
+            data: LedgerEntryData::ContractCode(ContractCodeEntry {
+                ext: ContractCodeEntryExt::V0,
+                hash: Hash([4u8; 32]),
+                code: vec![0u8; 8].try_into().unwrap(),
+            }),
+            ext: LedgerEntryExt::V0,
+        };
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("bucket.xdr");
+        std::fs::write(
+            &path,
+            bucket_stream(&[BucketEntry::Liveentry(account), BucketEntry::Initentry(code.clone())]),
+        )
+        .unwrap();
+
+        let snapshot = LedgerSnapshot::from_file(&path, XdrEncoding::Base64).unwrap();
+        assert_eq!(snapshot.keys().len(), 2);
+        assert_eq!(snapshot.contract_code().unwrap(), vec![Rc::new(code)]);
+        // Only the code entry was decoded to be listed.
+        assert_eq!(snapshot.mapped_decoded(), Some((1, 2)));
+    }
+
     fn create_dummy_ledger_entry() -> LedgerEntry {
         use soroban_env_host::xdr::{
             AccountEntry, AccountId, LedgerEntryData, PublicKey, SequenceNumber, Thresholds,
@@ -303,16 +943,16 @@ mod tests {
             num_sub_entries: 0,
             inflation_dest: None,
             flags: 0,
-            home_domain: Default::default(),
+            home_domain: String32::default(),
             thresholds: Thresholds([1, 0, 0, 0]),
-            signers: Default::default(),
-            ext: Default::default(),
+            signers: VecM::default(),
+            ext: AccountEntryExt::default(),
         };
 
         LedgerEntry {
             last_modified_ledger_seq: 1,
             data: LedgerEntryData::Account(account_entry),
-            ext: Default::default(),
+            ext: LedgerEntryExt::default(),
         }
     }
 }
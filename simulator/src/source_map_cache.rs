@@ -39,17 +39,17 @@ pub struct SourceMapCache {
 }
 
 impl SourceMapCache {
-    /// Creates a new SourceMapCache with the default cache directory
+    /// Creates a new `SourceMapCache` with the default cache directory
     pub fn new() -> Result<Self, String> {
         let cache_dir = Self::get_default_cache_dir()?;
         Ok(Self { cache_dir })
     }
 
-    /// Creates a new SourceMapCache with a custom cache directory
+    /// Creates a new `SourceMapCache` with a custom cache directory
     pub fn with_cache_dir(cache_dir: PathBuf) -> Result<Self, String> {
         // Ensure the cache directory exists
         fs::create_dir_all(&cache_dir)
-            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+            .map_err(|e| format!("Failed to create cache directory: {e}"))?;
         Ok(Self { cache_dir })
     }
 
@@ -70,7 +70,7 @@ impl SourceMapCache {
 
     /// Gets the cache file path for a given WASM hash
     fn get_cache_path(&self, wasm_hash: &str) -> PathBuf {
-        self.cache_dir.join(format!("{}.bin", wasm_hash))
+        self.cache_dir.join(format!("{wasm_hash}.bin"))
     }
 
     /// Gets a cached source map entry if it exists and is valid.
@@ -92,16 +92,16 @@ impl SourceMapCache {
         let mut file = match File::open(&cache_path) {
             Ok(f) => f,
             Err(e) => {
-                eprintln!("Failed to open cache file: {}", e);
+                eprintln!("Failed to open cache file: {e}");
                 return None;
             }
         };
 
         let mut bytes = Vec::new();
         if let Err(e) = file.read_to_end(&mut bytes) {
-            eprintln!("Failed to read cache file: {}", e);
+            eprintln!("Failed to read cache file: {e}");
             return None;
-        };
+        }
 
         match bincode::deserialize(&bytes) {
             Ok(entry) => {
@@ -112,30 +112,30 @@ impl SourceMapCache {
                 Some(entry)
             }
             Err(e) => {
-                eprintln!("Failed to deserialize cache entry: {}", e);
+                eprintln!("Failed to deserialize cache entry: {e}");
                 None
             }
         }
     }
 
     /// Stores a source map entry in the cache
-    pub fn store(&self, entry: SourceMapCacheEntry) -> Result<(), String> {
+    pub fn store(&self, entry: &SourceMapCacheEntry) -> Result<(), String> {
         // Ensure cache directory exists
         fs::create_dir_all(&self.cache_dir)
-            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+            .map_err(|e| format!("Failed to create cache directory: {e}"))?;
 
         let cache_path = self.get_cache_path(&entry.wasm_hash);
 
         // Serialize the entry
         let bytes = bincode::serialize(&entry)
-            .map_err(|e| format!("Failed to serialize cache entry: {}", e))?;
+            .map_err(|e| format!("Failed to serialize cache entry: {e}"))?;
 
         // Write to file
         let mut file =
-            File::create(&cache_path).map_err(|e| format!("Failed to create cache file: {}", e))?;
+            File::create(&cache_path).map_err(|e| format!("Failed to create cache file: {e}"))?;
 
         file.write_all(&bytes)
-            .map_err(|e| format!("Failed to write cache file: {}", e))?;
+            .map_err(|e| format!("Failed to write cache file: {e}"))?;
 
         println!("Cached source map for WASM: {}", &entry.wasm_hash[..8]);
 
@@ -150,14 +150,14 @@ impl SourceMapCache {
 
         let mut count = 0;
         for entry in fs::read_dir(&self.cache_dir)
-            .map_err(|e| format!("Failed to read cache directory: {}", e))?
+            .map_err(|e| format!("Failed to read cache directory: {e}"))?
         {
-            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {e}"))?;
             let path = entry.path();
 
             if path.is_file() && path.extension().is_some_and(|ext| ext == "bin") {
                 fs::remove_file(&path)
-                    .map_err(|e| format!("Failed to delete cache file: {}", e))?;
+                    .map_err(|e| format!("Failed to delete cache file: {e}"))?;
                 count += 1;
             }
         }
@@ -174,14 +174,14 @@ impl SourceMapCache {
 
         let mut total_size = 0u64;
         for entry in fs::read_dir(&self.cache_dir)
-            .map_err(|e| format!("Failed to read cache directory: {}", e))?
+            .map_err(|e| format!("Failed to read cache directory: {e}"))?
         {
-            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {e}"))?;
             let path = entry.path();
 
             if path.is_file() {
                 let metadata = fs::metadata(&path)
-                    .map_err(|e| format!("Failed to get file metadata: {}", e))?;
+                    .map_err(|e| format!("Failed to get file metadata: {e}"))?;
                 total_size += metadata.len();
             }
         }
@@ -197,9 +197,9 @@ impl SourceMapCache {
 
         let mut entries = Vec::new();
         for entry in fs::read_dir(&self.cache_dir)
-            .map_err(|e| format!("Failed to read cache directory: {}", e))?
+            .map_err(|e| format!("Failed to read cache directory: {e}"))?
         {
-            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {e}"))?;
             let path = entry.path();
 
             if path.is_file() && path.extension().is_some_and(|ext| ext == "bin") {
@@ -209,7 +209,7 @@ impl SourceMapCache {
                     if file.read_to_end(&mut bytes).is_ok() {
                         if let Ok(cache_entry) = bincode::deserialize::<SourceMapCacheEntry>(&bytes)
                         {
-                            let file_size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                            let file_size = fs::metadata(&path).map_or(0, |m| m.len());
 
                             entries.push(CachedEntryInfo {
                                 wasm_hash: cache_entry.wasm_hash,
@@ -228,7 +228,7 @@ impl SourceMapCache {
     }
 
     /// Returns the cache directory path
-    pub fn get_cache_dir(&self) -> &PathBuf {
+    pub const fn get_cache_dir(&self) -> &PathBuf {
         &self.cache_dir
     }
 }
@@ -294,6 +294,7 @@ mod tests {
                 line: 42,
                 column: Some(10),
                 column_end: None,
+                github_link: None,
             },
         );
 
@@ -301,11 +302,11 @@ mod tests {
             wasm_hash: wasm_hash.clone(),
             has_symbols: true,
             mappings,
-            created_at: 1234567890,
+            created_at: 1_234_567_890,
         };
 
         // Store the entry
-        cache.store(entry.clone()).unwrap();
+        cache.store(&entry).unwrap();
 
         // Retrieve the entry — no_cache=false so cache is used normally
         let retrieved = cache.get(&wasm_hash, false).unwrap();
@@ -333,11 +334,11 @@ mod tests {
             wasm_hash: wasm_hash.clone(),
             has_symbols: true,
             mappings: HashMap::new(),
-            created_at: 1234567890,
+            created_at: 1_234_567_890,
         };
 
         // Store an entry so it exists on disk
-        cache.store(entry).unwrap();
+        cache.store(&entry).unwrap();
         assert!(cache.get(&wasm_hash, false).is_some());
 
         // With no_cache=true, it should return None even though cache exists
@@ -356,10 +357,10 @@ mod tests {
             wasm_hash: wasm_hash.clone(),
             has_symbols: true,
             mappings: HashMap::new(),
-            created_at: 1234567890,
+            created_at: 1_234_567_890,
         };
 
-        cache.store(entry).unwrap();
+        cache.store(&entry).unwrap();
         assert!(cache.get(&wasm_hash, false).is_some());
 
         let count = cache.clear().unwrap();
@@ -385,6 +386,7 @@ mod tests {
                 line: 42,
                 column: Some(10),
                 column_end: None,
+                github_link: None,
             },
         );
 
@@ -392,10 +394,10 @@ mod tests {
             wasm_hash,
             has_symbols: true,
             mappings,
-            created_at: 1234567890,
+            created_at: 1_234_567_890,
         };
 
-        cache.store(entry).unwrap();
+        cache.store(&entry).unwrap();
 
         let size = cache.get_cache_size().unwrap();
         assert!(size > 0);
@@ -415,10 +417,10 @@ mod tests {
             wasm_hash: wasm_hash.clone(),
             has_symbols: true,
             mappings: HashMap::new(),
-            created_at: 1234567890,
+            created_at: 1_234_567_890,
         };
 
-        cache.store(entry).unwrap();
+        cache.store(&entry).unwrap();
 
         let list = cache.list_cached().unwrap();
         assert_eq!(list.len(), 1);
@@ -20,7 +20,8 @@ pub struct SourceLocation {
     pub line: u32,
     pub column: Option<u32>,
     pub column_end: Option<u32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    // Always written: the source map cache is bincode, which cannot read
+    // back a struct with skipped fields.
     pub github_link: Option<String>,
 }
 
@@ -32,13 +33,14 @@ struct CachedLineEntry {
 }
 
 impl SourceMapper {
-    /// Creates a new SourceMapper with caching enabled
+    /// Creates a new `SourceMapper` with caching enabled
     pub fn new(wasm_bytes: Vec<u8>) -> Self {
         Self::new_with_options(wasm_bytes, false)
     }
 
-    /// Creates a new SourceMapper, bypassing the cache when `no_cache` is true.
+    /// Creates a new `SourceMapper`, bypassing the cache when `no_cache` is true.
     /// When `no_cache` is true, WASM debug symbols are always re-parsed from scratch.
+    #[allow(clippy::needless_pass_by_value)]
     pub fn new_with_options(wasm_bytes: Vec<u8>, no_cache: bool) -> Self {
         if no_cache {
             eprintln!("--no-cache: skipping cache, re-parsing WASM symbols from scratch.");
@@ -71,12 +73,10 @@ impl SourceMapper {
     }
 
     fn check_debug_symbols(wasm_bytes: &[u8]) -> bool {
-        if let Ok(obj_file) = object::File::parse(wasm_bytes) {
+        object::File::parse(wasm_bytes).is_ok_and(|obj_file| {
             obj_file.section_by_name(".debug_info").is_some()
                 && obj_file.section_by_name(".debug_line").is_some()
-        } else {
-            false
-        }
+        })
     }
 
     #[allow(deprecated)]
@@ -90,14 +90,10 @@ impl SourceMapper {
         };
 
         let dwarf_sections = Dwarf::load(|id: SectionId| -> Result<Cow<'_, [u8]>, gimli::Error> {
-            if let Some(section) = obj_file.section_by_name(id.name()) {
-                match section.uncompressed_data() {
-                    Ok(data) => Ok(data),
-                    Err(_) => Ok(Cow::Borrowed(&[])),
-                }
-            } else {
-                Ok(Cow::Borrowed(&[]))
-            }
+            Ok(obj_file
+                .section_by_name(id.name())
+                .and_then(|section| section.uncompressed_data().ok())
+                .unwrap_or(Cow::Borrowed(&[])))
         })
         .map_err(|err| format!("failed to load DWARF: {err}"))?;
 
@@ -166,12 +162,12 @@ impl SourceMapper {
 
                     let column = match row.column() {
                         ColumnType::LeftEdge => None,
-                        ColumnType::Column(column) => Some(column.get() as u32),
+                        ColumnType::Column(column) => u32::try_from(column.get()).ok(),
                     };
 
                     let location = SourceLocation {
                         file: file_name,
-                        line: line.get() as u32,
+                        line: u32::try_from(line.get()).unwrap_or(u32::MAX),
                         column,
                         column_end: None,
                         github_link: None,
@@ -198,12 +194,10 @@ impl SourceMapper {
         }
 
         cache.sort_by_key(|entry| entry.start);
-        Self::dedupe_same_address_entries(cache)
+        Ok(Self::dedupe_same_address_entries(cache))
     }
 
-    fn dedupe_same_address_entries(
-        entries: Vec<CachedLineEntry>,
-    ) -> Result<Vec<CachedLineEntry>, gimli::Error> {
+    fn dedupe_same_address_entries(entries: Vec<CachedLineEntry>) -> Vec<CachedLineEntry> {
         let mut deduped: Vec<CachedLineEntry> = Vec::with_capacity(entries.len());
         for entry in entries {
             if let Some(last) = deduped.last_mut() {
@@ -214,7 +208,7 @@ impl SourceMapper {
             }
             deduped.push(entry);
         }
-        Ok(deduped)
+        deduped
     }
 
     fn attr_value_to_string<R>(
@@ -261,6 +255,7 @@ impl SourceMapper {
         Some(location)
     }
 
+    #[allow(dead_code)]
     pub fn create_source_location(&self, file: String, line: u32, column: Option<u32>) -> SourceLocation {
         let github_link = self.git_repo
             .as_ref()
@@ -275,7 +270,7 @@ impl SourceMapper {
         }
     }
 
-    pub fn has_debug_symbols(&self) -> bool {
+    pub const fn has_debug_symbols(&self) -> bool {
         self.has_symbols
     }
 }
@@ -342,7 +337,7 @@ mod tests {
 
         let loc = mapper.map_wasm_offset_to_source(0x18).expect("mapping");
         assert_eq!(loc.line, 10);
-        assert_eq!(loc.column, 1);
+        assert_eq!(loc.column, Some(1));
 
         let loc = mapper.map_wasm_offset_to_source(0x25).expect("mapping");
         assert_eq!(loc.line, 20);
@@ -404,7 +399,7 @@ mod tests {
 
         {
             let mapper =
-                SourceMapper::new_with_options(wasm_bytes.clone(), false);
+                SourceMapper::new_with_options(wasm_bytes, false);
             assert!(!mapper.has_debug_symbols());
             let result = mapper.map_wasm_offset_to_source(0x1234);
             assert!(result.is_none());
@@ -430,10 +425,10 @@ mod tests {
             wasm_hash: wasm_hash.clone(),
             has_symbols: true,
             mappings,
-            created_at: 1234567890,
+            created_at: 1_234_567_890,
         };
 
-        cache.store(entry).unwrap();
+        cache.store(&entry).unwrap();
 
         let entries = cache.list_cached().unwrap();
         assert_eq!(entries.len(), 1);
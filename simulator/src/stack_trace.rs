@@ -6,10 +6,12 @@
 //! Exposes the Wasmi internal call stack directly on traps,
 //! bypassing Soroban Host abstractions for low-level debugging.
 
+use schemars::JsonSchema;
 use serde::Serialize;
+use std::fmt::Write as _;
 
 /// A single frame in a WASM call stack.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
 pub struct StackFrame {
     /// Index within the call stack (0 = innermost/trap site).
     pub index: usize,
@@ -24,7 +26,7 @@ pub struct StackFrame {
 }
 
 /// Categorised trap reason extracted from a raw error string.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq, Eq)]
 pub enum TrapKind {
     OutOfBoundsMemoryAccess,
     OutOfBoundsTableAccess,
@@ -40,7 +42,7 @@ pub enum TrapKind {
 }
 
 /// Structured stack trace emitted on a WASM trap.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct WasmStackTrace {
     /// Categorised trap reason.
     pub trap_kind: TrapKind,
@@ -53,7 +55,7 @@ pub struct WasmStackTrace {
 }
 
 impl WasmStackTrace {
-    /// Build a stack trace by parsing a raw HostError debug representation.
+    /// Build a stack trace by parsing a raw `HostError` debug representation.
     ///
     /// This extracts trap kind, function names, and offsets from the
     /// stringified error that Wasmi/Soroban produces.
@@ -64,7 +66,7 @@ impl WasmStackTrace {
             || error_debug.contains("ScError")
             || error_debug.contains("Error(WasmVm");
 
-        WasmStackTrace {
+        Self {
             trap_kind,
             raw_message: error_debug.to_string(),
             frames,
@@ -74,7 +76,7 @@ impl WasmStackTrace {
 
     /// Build a trace from a panic payload.
     pub fn from_panic(message: &str) -> Self {
-        WasmStackTrace {
+        Self {
             trap_kind: TrapKind::Unknown(message.to_string()),
             raw_message: message.to_string(),
             frames: vec![],
@@ -86,7 +88,7 @@ impl WasmStackTrace {
     pub fn display(&self) -> String {
         let mut out = String::new();
 
-        out.push_str(&format!("Trap: {}\n", self.trap_kind_label()));
+        let _ = writeln!(out, "Trap: {}", self.trap_kind_label());
 
         if self.soroban_wrapped {
             out.push_str("  (error passed through Soroban Host layer)\n");
@@ -97,19 +99,19 @@ impl WasmStackTrace {
         } else {
             out.push_str("  Call stack (most recent call last):\n");
             for frame in &self.frames {
-                out.push_str(&format!("    #{}: ", frame.index));
+                let _ = write!(out, "    #{}: ", frame.index);
                 if let Some(ref name) = frame.func_name {
                     out.push_str(name);
                 } else if let Some(idx) = frame.func_index {
-                    out.push_str(&format!("func[{}]", idx));
+                    let _ = write!(out, "func[{idx}]");
                 } else {
                     out.push_str("<unknown>");
                 }
                 if let Some(offset) = frame.wasm_offset {
-                    out.push_str(&format!(" @ 0x{:x}", offset));
+                    let _ = write!(out, " @ 0x{offset:x}");
                 }
                 if let Some(ref module) = frame.module {
-                    out.push_str(&format!(" in {}", module));
+                    let _ = write!(out, " in {module}");
                 }
                 out.push('\n');
             }
@@ -117,7 +119,7 @@ impl WasmStackTrace {
         out
     }
 
-    fn trap_kind_label(&self) -> &str {
+    const fn trap_kind_label(&self) -> &str {
         match &self.trap_kind {
             TrapKind::OutOfBoundsMemoryAccess => "out of bounds memory access",
             TrapKind::OutOfBoundsTableAccess => "out of bounds table access",
@@ -183,7 +185,7 @@ fn extract_frames(error_debug: &str) -> Vec<StackFrame> {
         }
 
         // Match Wasmi-style "wasm backtrace:" header followed by frames
-        if trimmed.starts_with("func[") || trimmed.starts_with("<") {
+        if trimmed.starts_with("func[") || trimmed.starts_with('<') {
             if let Some(frame) = try_parse_bare_frame(trimmed, frames.len()) {
                 frames.push(frame);
             }
@@ -241,11 +243,9 @@ fn parse_frame_body(body: &str) -> (Option<String>, Option<u32>, Option<u64>) {
     let mut wasm_offset: Option<u64> = None;
 
     // Split on " @ " to separate name from offset
-    let (name_part, offset_part) = if let Some(idx) = body.find(" @ ") {
-        (&body[..idx], Some(&body[idx + 3..]))
-    } else {
-        (body, None)
-    };
+    let (name_part, offset_part) = body
+        .split_once(" @ ")
+        .map_or((body, None), |(name, offset)| (name, Some(offset)));
 
     // Parse offset
     if let Some(off) = offset_part {
@@ -280,20 +280,19 @@ pub fn decode_error(msg: &str) -> String {
     let trace = WasmStackTrace::from_host_error(msg);
     let label = trace.trap_kind_label();
 
-    if label != "unknown trap" {
-        format!("VM Trap: {} -- {}", capitalise_first(label), msg)
+    if label == "unknown trap" {
+        format!("Error: {msg}")
     } else {
-        format!("Error: {}", msg)
+        format!("VM Trap: {} -- {}", capitalise_first(label), msg)
     }
 }
 
 #[allow(dead_code)]
 fn capitalise_first(s: &str) -> String {
     let mut chars = s.chars();
-    match chars.next() {
-        None => String::new(),
-        Some(c) => c.to_uppercase().to_string() + chars.as_str(),
-    }
+    chars
+        .next()
+        .map_or_else(String::new, |c| c.to_uppercase().to_string() + chars.as_str())
 }
 
 #[cfg(test)]
@@ -0,0 +1,180 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! `StrKey` rendering for Stellar addresses.
+//!
+//! Output should always show addresses the way wallets and explorers do
+//! (`G...` accounts, `C...` contracts) rather than Rust Debug output of the
+//! underlying XDR.
+
+use soroban_env_host::xdr::{
+    AccountId, ContractId, Hash, MuxedAccount, PublicKey, ScAddress, Uint256,
+};
+
+/// Render a 32-byte contract hash as a `C...` `StrKey`.
+#[must_use]
+pub fn contract_strkey(id: &ContractId) -> String {
+    stellar_strkey::Contract(id.0 .0).to_string()
+}
+
+/// Render an account ID as a `G...` `StrKey`.
+#[must_use]
+pub fn account_strkey(account: &AccountId) -> String {
+    let AccountId(PublicKey::PublicKeyTypeEd25519(key)) = account;
+    stellar_strkey::ed25519::PublicKey(key.0).to_string()
+}
+
+/// Render a transaction-level account as a `G...` or, when muxed, `M...`
+/// `StrKey`.
+#[must_use]
+pub fn muxed_account_strkey(account: &MuxedAccount) -> String {
+    match account {
+        MuxedAccount::Ed25519(key) => stellar_strkey::ed25519::PublicKey(key.0).to_string(),
+        MuxedAccount::MuxedEd25519(muxed) => stellar_strkey::ed25519::MuxedAccount {
+            ed25519: muxed.ed25519.0,
+            id: muxed.id,
+        }
+        .to_string(),
+    }
+}
+
+/// Render raw contract hash bytes as a `C...` `StrKey`, rejecting anything
+/// but 32 bytes.
+///
+/// # Errors
+/// Fails unless `bytes` is 32 bytes long.
+pub fn contract_strkey_from_bytes(bytes: &[u8]) -> Result<String, String> {
+    Ok(stellar_strkey::Contract(key_bytes(bytes, "contract ID")?).to_string())
+}
+
+/// Render a raw ed25519 public key as a `G...` `StrKey`, rejecting anything
+/// but 32 bytes.
+///
+/// # Errors
+/// Fails unless `bytes` is 32 bytes long.
+pub fn account_strkey_from_bytes(bytes: &[u8]) -> Result<String, String> {
+    Ok(stellar_strkey::ed25519::PublicKey(key_bytes(bytes, "account public key")?).to_string())
+}
+
+/// Render a raw ed25519 public key and multiplexing ID as an `M...` `StrKey`,
+/// rejecting keys that are not 32 bytes.
+///
+/// # Errors
+/// Fails unless `bytes` is 32 bytes long.
+pub fn muxed_strkey_from_bytes(bytes: &[u8], id: u64) -> Result<String, String> {
+    Ok(stellar_strkey::ed25519::MuxedAccount {
+        ed25519: key_bytes(bytes, "muxed account public key")?,
+        id,
+    }
+    .to_string())
+}
+
+fn key_bytes(bytes: &[u8], what: &str) -> Result<[u8; 32], String> {
+    <[u8; 32]>::try_from(bytes)
+        .map_err(|_| format!("{what} must be 32 bytes, got {}", bytes.len()))
+}
+
+/// Render any `ScAddress` as a `StrKey`, falling back to Debug output for
+/// address kinds that have no `StrKey` form yet.
+#[must_use]
+pub fn sc_address_strkey(address: &ScAddress) -> String {
+    match address {
+        ScAddress::Account(account) => account_strkey(account),
+        ScAddress::Contract(id) => contract_strkey(id),
+        ScAddress::MuxedAccount(muxed) => stellar_strkey::ed25519::MuxedAccount {
+            ed25519: muxed.ed25519.0,
+            id: muxed.id,
+        }
+        .to_string(),
+        other => format!("{other:?}"),
+    }
+}
+
+/// Parse a `G...` account or `C...` contract `StrKey` into an `ScAddress`.
+///
+/// # Errors
+/// Fails if `strkey` is not a valid account or contract `StrKey`.
+pub fn parse_sc_address(strkey: &str) -> Result<ScAddress, String> {
+    match stellar_strkey::Strkey::from_string(strkey) {
+        Ok(stellar_strkey::Strkey::PublicKeyEd25519(key)) => Ok(ScAddress::Account(AccountId(
+            PublicKey::PublicKeyTypeEd25519(Uint256(key.0)),
+        ))),
+        Ok(stellar_strkey::Strkey::Contract(contract)) => {
+            Ok(ScAddress::Contract(ContractId(Hash(contract.0))))
+        }
+        Ok(_) => Err(format!("'{strkey}' is not an account or contract address")),
+        Err(e) => Err(format!("invalid StrKey '{strkey}': {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contract_strkey_prefix() {
+        let key = contract_strkey(&ContractId(Hash([0u8; 32])));
+        assert!(key.starts_with('C'));
+        assert_eq!(key.len(), 56);
+    }
+
+    #[test]
+    fn test_parse_sc_address_round_trips() {
+        let contract = ScAddress::Contract(ContractId(Hash([9u8; 32])));
+        assert_eq!(parse_sc_address(&sc_address_strkey(&contract)), Ok(contract));
+
+        let account = ScAddress::Account(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(
+            [4u8; 32],
+        ))));
+        assert_eq!(parse_sc_address(&sc_address_strkey(&account)), Ok(account));
+
+        assert!(parse_sc_address("not a strkey").is_err());
+    }
+
+    #[test]
+    fn test_known_vectors_round_trip() {
+        let contract = "CA3D5KRYM6CB7OWQ6TWYRR3Z4T7GNZLKERYNZGGA5SOAOPIFY6YQGAXE";
+        let stellar_strkey::Strkey::Contract(bytes) =
+            stellar_strkey::Strkey::from_string(contract).unwrap()
+        else {
+            panic!("not a contract StrKey");
+        };
+        assert_eq!(contract_strkey_from_bytes(&bytes.0).unwrap(), contract);
+        assert_eq!(contract_strkey(&ContractId(Hash(bytes.0))), contract);
+
+        let account = "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ";
+        let stellar_strkey::Strkey::PublicKeyEd25519(key) =
+            stellar_strkey::Strkey::from_string(account).unwrap()
+        else {
+            panic!("not an account StrKey");
+        };
+        assert_eq!(account_strkey_from_bytes(&key.0).unwrap(), account);
+
+        let muxed = "MA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJUAAAAAAAAAAAACJUQ";
+        let stellar_strkey::Strkey::MuxedAccountEd25519(parsed) =
+            stellar_strkey::Strkey::from_string(muxed).unwrap()
+        else {
+            panic!("not a muxed StrKey");
+        };
+        assert_eq!(parsed.ed25519, key.0);
+        assert_eq!(muxed_strkey_from_bytes(&key.0, parsed.id).unwrap(), muxed);
+    }
+
+    #[test]
+    fn test_wrong_key_length_is_an_error() {
+        assert_eq!(
+            contract_strkey_from_bytes(&[0u8; 31]),
+            Err("contract ID must be 32 bytes, got 31".to_string())
+        );
+        assert!(account_strkey_from_bytes(&[]).is_err());
+        assert!(muxed_strkey_from_bytes(&[0u8; 33], 1).is_err());
+    }
+
+    #[test]
+    fn test_account_strkey_prefix() {
+        let account = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([1u8; 32])));
+        let key = sc_address_strkey(&ScAddress::Account(account));
+        assert!(key.starts_with('G'));
+        assert_eq!(key.len(), 56);
+    }
+}
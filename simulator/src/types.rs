@@ -3,16 +3,55 @@
 
 #![allow(dead_code)]
 
+use crate::cost_params::CostParamOverride;
+use crate::fees::{FeeConfigJson, FeeEstimate};
 use crate::gas_optimizer::OptimizationReport;
+use crate::scval_json::ScValJson;
 use crate::stack_trace::WasmStackTrace;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct SimulationRequest {
+    /// Any JSON value, echoed back as the response's `id` so a caller that
+    /// pipelines requests in `--serve` mode can match up the responses.
+    #[serde(default)]
+    pub id: Option<serde_json::Value>,
     pub envelope_xdr: String,
     pub result_meta_xdr: String,
+    /// `"simulate"` (the default) runs the envelope with its auth entries
+    /// enforced. `"recording_auth"` ignores them and reports the entries
+    /// the invocation would need in `recorded_auth` instead. `"preflight"`
+    /// does the same and reports the discovered footprint and resources in
+    /// `footprint` and `transaction_data`, without checking them against
+    /// any the envelope declares. `"diff"` simulates as usual and compares
+    /// the outcome with `result_meta_xdr`, listing mismatches in
+    /// `differences`. `"direct_invoke"` requests carry a
+    /// `DirectInvokeRequest` in place of `envelope_xdr`.
+    #[serde(default)]
+    pub mode: Option<String>,
     pub ledger_entries: Option<HashMap<String, String>>,
+    /// File of ledger entries loaded before `ledger_entries`: a JSON object
+    /// shaped like `ledger_entries`, or a stellar-core bucket file. Entries
+    /// in `ledger_entries` replace file entries with the same key.
+    #[serde(default)]
+    pub snapshot_path: Option<String>,
+    /// Soroban RPC endpoint to fetch the entries a simulation reports in
+    /// `missing_entries` and `probed_entries` from. They are added to
+    /// `ledger_entries` and the simulation is retried, for a bounded number
+    /// of rounds. Needs the simulator to be built with the `rpc` feature.
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    /// Whether an undecodable `ledger_entries` pair fails the request
+    /// (the default). When false, such pairs are listed in
+    /// `skipped_entries` and the simulation runs without them.
+    #[serde(default)]
+    pub strict_entries: Option<bool>,
+    /// Contract storage entries described in JSON rather than XDR. Loaded
+    /// alongside `ledger_entries`; a key given in both takes this value.
+    #[serde(default)]
+    pub contract_data: Option<Vec<ContractDataJson>>,
     pub contract_wasm: Option<String>,
     pub wasm_path: Option<String>, // Added for local loading
     pub enable_optimization_advisor: bool,
@@ -26,19 +65,169 @@ pub struct SimulationRequest {
     pub mock_signature_verification: Option<bool>,
     #[serde(default)]
     pub enable_coverage: bool,
+    /// Assemble `trace`: the steps the host's events record, followed by
+    /// the storage reads and writes behind the footprint and state changes.
+    /// Left unset, the trace is never built.
+    #[serde(default)]
+    pub trace: Option<bool>,
     #[serde(default)]
     pub coverage_lcov_path: Option<String>,
     pub resource_calibration: Option<ResourceCalibration>,
     /// Optional hard memory limit in bytes. If set, the simulator will panic
     /// when memory consumption exceeds this limit, simulating live network constraints.
+    /// Also applied as the host budget's memory ceiling.
     pub memory_limit: Option<u64>,
+    /// Optional CPU instruction ceiling applied to the host budget.
+    #[serde(default)]
+    pub cpu_limit: Option<u64>,
     #[serde(default)]
     pub restore_preamble: Option<serde_json::Value>,
     #[serde(default)]
     pub include_linear_memory: bool,
+    /// Network passphrase used to derive the network ID for contract
+    /// addresses and auth payload hashes. Defaults to the public network.
+    #[serde(default)]
+    pub network_passphrase: Option<String>,
+    /// Ledger the simulation pretends to run in. Unset fields keep the
+    /// defaults for the latest supported protocol.
+    #[serde(default)]
+    pub ledger_info: Option<LedgerInfoJson>,
+    /// Overrides for the network fee settings used by `fee_estimate`.
+    #[serde(default)]
+    pub fee_config: Option<FeeConfigJson>,
+    /// Replacement CPU cost coefficients keyed by cost type name, e.g.
+    /// `WasmInsnExec`, to model a proposed cost table. Other cost types
+    /// keep the host's defaults.
+    #[serde(default)]
+    pub cost_params_override: Option<HashMap<String, CostParamOverride>>,
+    /// Text encoding of every XDR field: `"base64"` (default) or `"hex"`.
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// Bypass the on-disk source map cache when resolving debug symbols.
+    #[serde(default)]
+    pub no_cache: Option<bool>,
+    /// Upper bound, in serialized bytes, on the event, trace and log arrays
+    /// together. Entries past it are dropped, leaving a single
+    /// `"...truncated (N more)"` marker in each string array and a warning
+    /// for each other one.
+    #[serde(default)]
+    pub max_output_bytes: Option<usize>,
+    /// Wall-clock limit for the whole simulation, in milliseconds. The host
+    /// budget already bounds CPU; this only guards against pathological
+    /// cases where metering is too coarse to stop a run in reasonable time.
+    /// A timed-out run cannot be interrupted mid-invocation: it keeps
+    /// running in the background until its next operation or until its CPU
+    /// budget runs out. To bound that, `cpu_limit` is capped at 10M
+    /// instructions per millisecond of the timeout.
+    #[serde(default)]
+    pub wall_clock_timeout_ms: Option<u64>,
+    /// Hex-encoded 32-byte seed for the host PRNG behind `env.prng()`.
+    /// Defaults to all zeros, so simulations are reproducible.
+    #[serde(default)]
+    pub prng_seed: Option<String>,
+    /// `C...` contract addresses whose events are kept in
+    /// `contract_events`. Contract events from any other contract are
+    /// dropped; system events are unaffected.
+    #[serde(default)]
+    pub event_filter_contracts: Option<Vec<String>>,
+    /// Simulate every operation on its own against the untouched ledger
+    /// state, reporting one independent result per operation. This is an
+    /// exploratory, non-atomic mode: on chain, operations see each other's
+    /// writes and one failure aborts the whole transaction.
+    #[serde(default)]
+    pub isolate_operations: Option<bool>,
+    /// Event names, matched against the `event_name` of contract and system
+    /// events, that stop the simulation with an `ABORTED_ON_EVENT` error.
+    /// Events are checked after each operation, so the operation emitting
+    /// one runs to completion; only later operations are skipped.
+    #[serde(default)]
+    pub abort_on_event: Option<Vec<String>>,
+    /// Reject an envelope without operations with `INVALID_ENVELOPE`. By
+    /// default it is simulated as doing nothing, with a warning.
+    #[serde(default)]
+    pub require_operations: Option<bool>,
+}
+
+/// One contract storage entry, e.g.
+/// `{"contract_id": "C...", "key": {"type": "symbol", "value": "k"},
+///   "durability": "persistent", "value": {"type": "u32", "value": 7}}`.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct ContractDataJson {
+    pub contract_id: String,
+    pub key: ScValJson,
+    /// `"persistent"` or `"temporary"`.
+    pub durability: String,
+    pub value: ScValJson,
+    /// Ledger the entry stays live until. Defaults to never expiring
+    /// within the simulated ledger.
+    #[serde(default)]
+    pub live_until_ledger: Option<u32>,
+}
+
+/// Request to check a Wasm module without simulating a transaction, sent
+/// as `{"mode": "validate_wasm", "wasm_base64": ...}`.
+#[derive(Debug, Deserialize)]
+pub struct WasmValidationRequest {
+    pub wasm_base64: String,
+}
+
+/// Request to call one contract function without building an envelope.
+///
+/// It is sent as `{"mode": "direct_invoke", "contract_id": "C...", ...}`.
+/// Any other `SimulationRequest` field, such as `ledger_entries`, applies
+/// as usual.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DirectInvokeRequest {
+    /// `C...` `StrKey` of the contract to call.
+    pub contract_id: String,
+    pub function: String,
+    /// Arguments in the tagged `ScVal` JSON form.
+    #[serde(default)]
+    pub args: Vec<ScValJson>,
+    #[serde(default)]
+    pub encoding: Option<String>,
+}
+
+/// Outcome of a `validate_wasm` request.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct WasmValidation {
+    pub valid: bool,
+    /// Hex SHA-256 of the module, i.e. its on-ledger code hash.
+    pub hash: String,
+    pub size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Caller-supplied ledger header values for time- or sequence-dependent
+/// contracts.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Default)]
+pub struct LedgerInfoJson {
+    pub sequence_number: Option<u32>,
+    pub timestamp: Option<u64>,
+    pub protocol_version: Option<u32>,
+    pub base_reserve: Option<u32>,
+}
+
+impl LedgerInfoJson {
+    /// Overwrite the fields of `info` that this override sets.
+    pub const fn apply(&self, info: &mut soroban_env_host::LedgerInfo) {
+        if let Some(sequence_number) = self.sequence_number {
+            info.sequence_number = sequence_number;
+        }
+        if let Some(timestamp) = self.timestamp {
+            info.timestamp = timestamp;
+        }
+        if let Some(protocol_version) = self.protocol_version {
+            info.protocol_version = protocol_version;
+        }
+        if let Some(base_reserve) = self.base_reserve {
+            info.base_reserve = base_reserve;
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct ResourceCalibration {
     pub sha256_fixed: u64,
     pub sha256_per_byte: u64,
@@ -47,20 +236,164 @@ pub struct ResourceCalibration {
     pub ed25519_fixed: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize, JsonSchema)]
 pub struct SimulationResponse {
+    /// The request's `id`, echoed back unchanged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
     pub status: String,
     pub error: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<ErrorCode>")]
     pub error_code: Option<String>,
+    /// Likely causes of the error and how to fix them, added by
+    /// `--explain` for common error codes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+    /// `ScErrorType` of the host error behind a failed invocation, e.g.
+    /// `"Contract"` or `"Storage"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+    /// `N` of an `Error(Contract, #N)` the contract failed with, i.e. the
+    /// value of its `#[contracterror]` variant.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_contract_code: Option<u32>,
+    /// Value returned by the invoked host function, when execution succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_value: Option<ScValJson>,
+    /// Base64 XDR of the returned `ScVal`, for tooling that re-parses it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_value_xdr: Option<String>,
+    /// Where `return_value` was read from: `invocation`, the host's direct
+    /// result, or `fn_return`, the top-level call's diagnostic event, used
+    /// when no direct result is available.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_value_source: Option<String>,
+    /// Hex SHA-256 over the status, return value, contract events and state
+    /// changes, in a canonical order. Deterministic simulations of the same
+    /// request share it, so it can key a result cache.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_hash: Option<String>,
+    /// Result of a `validate_wasm` request; absent for simulations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wasm_validation: Option<WasmValidation>,
+    /// Source account the operations ran as. For a fee-bump envelope this
+    /// is the inner transaction's source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_account: Option<SourceAccountJson>,
+    /// Effective ledger settings the simulation ran under.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<EnvironmentJson>,
+    /// Whether the transaction's time and ledger bounds admit the simulated
+    /// ledger, i.e. whether the network would accept it now. `None` when
+    /// the envelope was not evaluated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preconditions_ok: Option<bool>,
+    /// Why `preconditions_ok` is false, one entry per unmet bound.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub precondition_failures: Vec<String>,
+    /// Executable and instance storage of the invoked contract, when its
+    /// instance entry was supplied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_info: Option<InstanceInfo>,
+    /// Outcome of each `InvokeHostFunction` operation, in execution order.
+    pub operation_results: Vec<OperationResult>,
+    /// Classic operations in the envelope, which are recognised but skipped.
+    pub classic_operations: Vec<ClassicOperation>,
+    /// `C...` `StrKey` of the contract deployed by a `CreateContract` or
+    /// `CreateContractV2` operation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_contract_id: Option<String>,
+    /// Hex SHA-256 hash of the Wasm installed by an `UploadContractWasm` operation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uploaded_wasm_hash: Option<String>,
+    /// Ledger keys the simulation touched, as recorded by host storage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub footprint: Option<LedgerFootprint>,
+    /// True when a successful simulation changed nothing: no writes, TTL
+    /// changes, classic operations or contract events. Such a call needs no
+    /// submitted transaction.
+    pub read_only: bool,
+    /// Ledger entries written by the simulation, before and after.
+    pub state_changes: Vec<StateChange>,
+    /// Base64 keys of archived persistent entries the transaction touched.
+    /// They must be restored before the invocation can succeed.
+    pub restore_required: Vec<String>,
+    /// Entries whose TTL an `ExtendFootprintTtl` or `RestoreFootprint`
+    /// operation moved, in operation order.
+    pub ttl_changes: Vec<TtlChange>,
+    /// Base64 key of the entry whose read failed the invocation because
+    /// neither `ledger_entries` nor `contract_data` supplied it. Fetch it and
+    /// retry.
+    pub missing_entries: Vec<String>,
+    /// Base64 keys the simulation looked up, e.g. with `has`, and found in
+    /// neither `ledger_entries` nor `contract_data`, without failing for it.
+    /// Their absence may be what the contract expects; supply them if the
+    /// ledger holds them.
+    pub probed_entries: Vec<String>,
+    /// `ledger_entries` pairs left out because they failed to decode, with
+    /// `strict_entries: false`.
+    pub skipped_entries: Vec<SkippedEntry>,
+    /// Authorization entries blamed for an auth failure, when there was one.
+    pub auth_errors: Vec<AuthErrorJson>,
+    /// Authorization entries recorded in `recording_auth` mode, ready to be
+    /// signed and attached to the operations.
+    pub recorded_auth: Vec<RecordedAuthJson>,
+    /// Auth nonces the simulation consumed. Once the transaction applies,
+    /// the entries that used them cannot be replayed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub nonces_consumed: Vec<ConsumedNonce>,
+    /// The original execution decoded from `result_meta_xdr`, if given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recorded_meta: Option<RecordedMeta>,
+    /// In `diff` mode, each way the simulation departed from
+    /// `recorded_meta`; empty when they agree.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub differences: Option<Vec<MetaDifference>>,
+    /// Resource fee the network would charge for this simulation's footprint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_estimate: Option<FeeEstimate>,
+    /// Base64 `SorobanTransactionData` declaring the simulated footprint,
+    /// resources and resource fee, ready to set as the transaction's
+    /// Soroban extension before signing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_data: Option<String>,
+    /// Metered host cost, reported even when the invocation failed part-way.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceUsage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub lcov_report: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub lcov_report_path: Option<String>,
     pub events: Vec<String>,
+    /// Events decoded into typed JSON, alongside their raw XDR.
+    pub structured_events: Vec<ContractEventJson>,
+    /// Contract and system events; diagnostics are excluded.
+    pub contract_events: Vec<ContractEventJson>,
+    /// Debug logs and call traces emitted at `DiagnosticLevel::Debug`.
     pub diagnostic_events: Vec<DiagnosticEvent>,
+    /// `C...` `StrKeys` of every contract the invocation called, in order of
+    /// first call.
+    pub contracts_invoked: Vec<String>,
+    /// Deepest nesting of contract calls: 1 when the invoked contract
+    /// called no other, 0 when no contract ran.
+    pub max_call_depth: u32,
+    /// Contract functions reached during the simulation, as
+    /// `"C...::function"` in order of first call: the invoked entry point
+    /// and every cross-contract call the host observed.
+    pub coverage: Vec<String>,
     pub categorized_events: Vec<CategorizedEvent>,
+    /// Contract `log!` output, decoded to text; non-UTF-8 payloads as hex.
+    pub debug_logs: Vec<String>,
     pub logs: Vec<String>,
+    /// Advisory conditions that did not stop the simulation, such as
+    /// supplied ledger entries it never used. `logs` keeps the step-by-step
+    /// trace.
+    pub warnings: Vec<String>,
+    /// Number of operations in the simulated envelope, so an empty one is
+    /// not mistaken for a successful call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation_count: Option<usize>,
     pub flamegraph: Option<String>,
     pub optimization_report: Option<OptimizationReport>,
     pub budget_usage: Option<BudgetUsage>,
@@ -71,9 +404,450 @@ pub struct SimulationResponse {
     pub wasm_offset: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub linear_memory_dump: Option<String>,
+    /// Where the simulation spent its time. Reported with `--timings`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Timings>,
+    /// Calls, returns, logs and events in the order the host produced
+    /// them, followed by a summary of the storage reads and writes, which
+    /// the host does not order. Reported with `--trace`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Vec<TraceEntry>>,
+}
+
+/// One step of the host's execution, decoded from its event stream, or
+/// one storage access from the summary that follows those steps.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TraceEntry {
+    /// `fn_call`, `fn_return`, `log`, `error`, `emit_event`, or the leading
+    /// topic of any other diagnostic event. The trailing summary uses
+    /// `storage_get` for each key read and `storage_put` for each entry
+    /// written, in no particular order.
+    pub kind: String,
+    /// `C...` `StrKey` of the contract the step ran in; for `fn_call`, the
+    /// contract being called.
+    pub contract: Option<String>,
+    /// Human-readable arguments, e.g. `transfer(from, to, 10)` for a call,
+    /// `transfer -> Void` for a return or `Symbol(..) = U32(7)` for a
+    /// storage write.
+    pub detail: String,
+}
+
+/// Wall-clock time spent in each phase of a simulation, in nanoseconds.
+/// Host setup and response assembly make up the rest of `total_ns`.
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+pub struct Timings {
+    /// Decoding the request's XDR: envelope, result meta and contract Wasm.
+    pub parse_ns: u64,
+    /// Decoding ledger entries into the snapshot backing host storage.
+    pub storage_load_ns: u64,
+    /// Running the envelope's operations on the host.
+    pub invoke_ns: u64,
+    pub total_ns: u64,
+}
+
+/// Process exit status when the request itself was unusable: bad JSON,
+/// undecodable XDR, a malformed envelope, or unreadable input.
+pub const EXIT_INPUT_ERROR: u8 = 2;
+/// Process exit status when the request was valid but simulating it failed.
+pub const EXIT_SIMULATION_ERROR: u8 = 1;
+
+/// Stable, machine-readable classification of a failed simulation, sent
+/// as `error_code` next to the human-readable `error`.
+///
+/// Each code also fixes the binary's exit status: [`EXIT_INPUT_ERROR`] for
+/// `INVALID_JSON`, `INVALID_REQUEST`, `IO_ERROR`, `ENVELOPE_DECODE`,
+/// `INVALID_ENVELOPE`, `LEDGER_KEY_DECODE` and `XDR_LIMIT_EXCEEDED`, and [`EXIT_SIMULATION_ERROR`]
+/// for every other code. A successful response exits with 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The input was not JSON, or not a valid `SimulationRequest`.
+    InvalidJson,
+    /// The request was well-formed JSON but its options are inconsistent.
+    InvalidRequest,
+    /// Reading the input, writing the output or fetching from `rpc_url`
+    /// failed.
+    Io,
+    /// `envelope_xdr` could not be decoded.
+    EnvelopeDecode,
+    /// The envelope decoded but an operation in it is malformed.
+    InvalidEnvelope,
+    /// A `ledger_entries` key or value could not be decoded.
+    LedgerKeyDecode,
+    /// Request XDR is nested deeper or runs longer than the decoder's caps.
+    XdrLimitExceeded,
+    /// The host rejected the requested ledger info.
+    LedgerInfo,
+    /// Local Wasm could not be loaded or failed VM enforcement checks.
+    WasmLoad,
+    /// A `validate_wasm` request's module was rejected.
+    InvalidWasm,
+    /// A contract imports a host function newer than the ledger protocol.
+    ProtocolMismatch,
+    BudgetExceeded,
+    MemoryLimitExceeded,
+    ContractTrap,
+    AuthFailed,
+    /// A ledger entry the invocation needed is not in `ledger_entries`.
+    EntryMissing,
+    /// A persistent entry the invocation needed has expired; see
+    /// `restore_required`.
+    EntryArchived,
+    InsufficientFee,
+    /// Any other host error.
+    HostError,
+    /// The simulation ran past `wall_clock_timeout_ms`.
+    SimulationTimeout,
+    /// An operation emitted an event named in `abort_on_event`; checked
+    /// after the operation returned.
+    AbortedOnEvent,
+    /// The simulator itself panicked; the request may still be valid.
+    Panic,
+}
+
+impl ErrorCode {
+    pub const ALL: [Self; 22] = [
+        Self::InvalidJson,
+        Self::InvalidRequest,
+        Self::Io,
+        Self::EnvelopeDecode,
+        Self::InvalidEnvelope,
+        Self::LedgerKeyDecode,
+        Self::XdrLimitExceeded,
+        Self::LedgerInfo,
+        Self::WasmLoad,
+        Self::InvalidWasm,
+        Self::ProtocolMismatch,
+        Self::BudgetExceeded,
+        Self::MemoryLimitExceeded,
+        Self::ContractTrap,
+        Self::AuthFailed,
+        Self::EntryMissing,
+        Self::EntryArchived,
+        Self::InsufficientFee,
+        Self::HostError,
+        Self::SimulationTimeout,
+        Self::AbortedOnEvent,
+        Self::Panic,
+    ];
+
+    /// Look up the code whose [`as_str`](Self::as_str) is `s`.
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|code| code.as_str() == s)
+    }
+
+    /// What usually causes this failure and how to get past it, for the
+    /// codes common enough to have a standard remedy.
+    #[must_use]
+    pub const fn explanation(self) -> Option<&'static str> {
+        match self {
+            Self::EntryMissing => Some(
+                "The invocation read a ledger entry the request did not supply. Add the keys \
+                 listed in `missing_entries` with their entries to `ledger_entries`, or set \
+                 `rpc_url` to fetch them from the network.",
+            ),
+            Self::EntryArchived => Some(
+                "A persistent entry the invocation needs has expired. Submit a \
+                 RestoreFootprint operation for the keys in `restore_required` first.",
+            ),
+            Self::BudgetExceeded | Self::MemoryLimitExceeded => Some(
+                "The invocation ran out of CPU or memory budget. Raise `cpu_limit` or \
+                 `memory_limit` to see how much it needs, or reduce the work per call, e.g. \
+                 by bounding loops and storage reads; `budget_usage` shows what was consumed.",
+            ),
+            Self::AuthFailed => Some(
+                "An authorization the invocation required was missing or invalid. Run the \
+                 request with \"mode\": \"recording_auth\" to get the entries it needs in \
+                 `recorded_auth`; `auth_errors` names the entry at fault.",
+            ),
+            Self::ContractTrap => Some(
+                "The contract panicked or hit a Wasm trap. Check `debug_logs` and \
+                 `stack_trace` for where it stopped, and the arguments it was called with.",
+            ),
+            Self::ProtocolMismatch => Some(
+                "The contract uses host functions newer than the simulated protocol. Set \
+                 `ledger_info.protocol_version` to the network's protocol, or rebuild the \
+                 contract with an SDK for the older protocol.",
+            ),
+            Self::InsufficientFee => Some(
+                "The declared fee is below what the transaction needs. Raise the fee to at \
+                 least the required amount, using `fee_estimate` as a guide.",
+            ),
+            Self::XdrLimitExceeded => Some(
+                "The request XDR is nested deeper or is longer than the decoder allows. \
+                 Check the input is what you meant to send, or raise `--xdr-depth-limit` \
+                 and `--xdr-length-limit`.",
+            ),
+            _ => None,
+        }
+    }
+
+    /// Exit status the binary reports for a response with this code.
+    #[must_use]
+    pub const fn exit_code(self) -> u8 {
+        match self {
+            Self::InvalidJson
+            | Self::InvalidRequest
+            | Self::Io
+            | Self::EnvelopeDecode
+            | Self::InvalidEnvelope
+            | Self::LedgerKeyDecode
+            | Self::XdrLimitExceeded => EXIT_INPUT_ERROR,
+            _ => EXIT_SIMULATION_ERROR,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidJson => "INVALID_JSON",
+            Self::InvalidRequest => "INVALID_REQUEST",
+            Self::Io => "IO_ERROR",
+            Self::EnvelopeDecode => "ENVELOPE_DECODE",
+            Self::InvalidEnvelope => "INVALID_ENVELOPE",
+            Self::LedgerKeyDecode => "LEDGER_KEY_DECODE",
+            Self::XdrLimitExceeded => "XDR_LIMIT_EXCEEDED",
+            Self::LedgerInfo => "LEDGER_INFO",
+            Self::WasmLoad => "WASM_LOAD",
+            Self::InvalidWasm => "INVALID_WASM",
+            Self::ProtocolMismatch => "PROTOCOL_MISMATCH",
+            Self::BudgetExceeded => "BUDGET_EXCEEDED",
+            // Predates the other codes; kept for existing consumers.
+            Self::MemoryLimitExceeded => "ERR_MEMORY_LIMIT_EXCEEDED",
+            Self::ContractTrap => "CONTRACT_TRAP",
+            Self::AuthFailed => "AUTH_FAILED",
+            Self::EntryMissing => "ENTRY_MISSING",
+            Self::EntryArchived => "ENTRY_ARCHIVED",
+            Self::InsufficientFee => "INSUFFICIENT_FEE",
+            Self::HostError => "HOST_ERROR",
+            Self::SimulationTimeout => "SIMULATION_TIMEOUT",
+            Self::AbortedOnEvent => "ABORTED_ON_EVENT",
+            Self::Panic => "INTERNAL_PANIC",
+        }
+    }
+}
+
+impl JsonSchema for ErrorCode {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "ErrorCode".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        let codes: Vec<&str> = Self::ALL.iter().map(|code| code.as_str()).collect();
+        schemars::json_schema!({
+            "type": "string",
+            "description": "Machine-readable error category, reported next to `error`.",
+            "enum": codes,
+        })
+    }
+}
+
+impl SimulationResponse {
+    /// Build a bare error response with the given code and message and no
+    /// results.
+    #[must_use]
+    pub fn error(code: ErrorCode, message: String) -> Self {
+        Self {
+            status: "error".to_string(),
+            error: Some(message),
+            error_code: Some(code.as_str().to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Exit status for this response: 0 unless `status` is `"error"`, then
+    /// as classified by its [`ErrorCode`].
+    pub fn exit_code(&self) -> u8 {
+        if self.status != "error" {
+            return 0;
+        }
+        self.error_code
+            .as_deref()
+            .and_then(ErrorCode::parse)
+            .map_or(EXIT_SIMULATION_ERROR, ErrorCode::exit_code)
+    }
+
+    /// Drop diagnostic events from every event list, and the `log!`
+    /// output carried by them, for callers that only want ledger-visible
+    /// events.
+    #[must_use]
+    pub fn without_diagnostics(mut self) -> Self {
+        if self.status == "error" && !self.diagnostic_events.is_empty() {
+            self.warnings.push(
+                "Diagnostic events that may explain this error were omitted; \
+                 run without --no-diagnostics to see them"
+                    .to_string(),
+            );
+        }
+        let is_diagnostic = |event: &ContractEventJson| event.event_type == "diagnostic";
+        // `events` lists every host event in emission order, which is what
+        // the structured events' `index` counts.
+        let diagnostic: HashSet<usize> = self
+            .structured_events
+            .iter()
+            .filter(|event| is_diagnostic(event))
+            .map(|event| event.index)
+            .collect();
+        self.events = std::mem::take(&mut self.events)
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !diagnostic.contains(index))
+            .map(|(_, event)| event)
+            .collect();
+        self.structured_events.retain(|event| !is_diagnostic(event));
+        self.categorized_events
+            .retain(|event| event.event.event_type != "diagnostic");
+        self.diagnostic_events.clear();
+        self.debug_logs.clear();
+        self
+    }
+
+    /// Drop the phase timings, which only `--timings` asks for.
+    #[must_use]
+    pub const fn without_timings(mut self) -> Self {
+        self.timings = None;
+        self
+    }
+
+    /// Set `explanation` to the hint for this response's error code, if
+    /// it has one.
+    #[must_use]
+    pub fn with_explanation(mut self) -> Self {
+        self.explanation = self
+            .error_code
+            .as_deref()
+            .and_then(ErrorCode::parse)
+            .and_then(ErrorCode::explanation)
+            .map(str::to_string);
+        self
+    }
+
+    /// Drop the invocation trace, which only `--trace` asks for.
+    #[must_use]
+    pub fn without_trace(mut self) -> Self {
+        self.trace = None;
+        self
+    }
+
+    /// Truncate `events`, the decoded event arrays, `trace`, `logs` and
+    /// `debug_logs`, in that order, so that their entries serialize to at
+    /// most `max_bytes` bytes in total. Each cut string array ends with a
+    /// marker counting what was dropped; each cut typed array gets a warning
+    /// saying the same. `status`, `error` and every other field are left
+    /// whole.
+    #[must_use]
+    pub fn with_output_limit(mut self, max_bytes: usize) -> Self {
+        let mut remaining = max_bytes;
+        let dropped = keep_within(&mut self.events, &mut remaining);
+        if dropped > 0 {
+            self.events.push(format!("...truncated ({dropped} more)"));
+        }
+        let cut = [
+            ("structured_events", keep_within(&mut self.structured_events, &mut remaining)),
+            ("contract_events", keep_within(&mut self.contract_events, &mut remaining)),
+            ("diagnostic_events", keep_within(&mut self.diagnostic_events, &mut remaining)),
+            ("categorized_events", keep_within(&mut self.categorized_events, &mut remaining)),
+            (
+                "trace",
+                self.trace.as_mut().map_or(0, |trace| keep_within(trace, &mut remaining)),
+            ),
+        ];
+        for entries in [&mut self.logs, &mut self.debug_logs] {
+            let dropped = keep_within(entries, &mut remaining);
+            if dropped > 0 {
+                entries.push(format!("...truncated ({dropped} more)"));
+            }
+        }
+        for (field, dropped) in cut.into_iter().filter(|(_, dropped)| *dropped > 0) {
+            self.warnings.push(format!(
+                "`{field}` truncated to fit max_output_bytes ({dropped} more)"
+            ));
+        }
+        self
+    }
+
+    /// The essential result alone, as `--quiet` reports it.
+    #[must_use]
+    pub fn quiet(&self) -> QuietResponse<'_> {
+        QuietResponse {
+            id: self.id.as_ref(),
+            status: &self.status,
+            error: self.error.as_deref(),
+            return_value: self.return_value.as_ref(),
+            return_value_xdr: self
+                .return_value_xdr
+                .as_deref()
+                .filter(|_| self.return_value.is_none()),
+            events: &self.events,
+        }
+    }
+
+    /// Report `events` as each event's base64 `ContractEvent` XDR instead
+    /// of its text rendering, for consumers that re-ingest them.
+    #[must_use]
+    pub fn with_xdr_events(mut self) -> Self {
+        self.events = self
+            .structured_events
+            .iter()
+            .map(|event| event.xdr.clone())
+            .collect();
+        self
+    }
+
+    /// Report return values only as base64 `ScVal` XDR, in
+    /// `return_value_xdr`, dropping the JSON rendering, which cannot carry
+    /// every `ScVal` exactly.
+    #[must_use]
+    pub fn with_xdr_return_value(mut self) -> Self {
+        self.return_value = None;
+        for result in &mut self.operation_results {
+            result.return_value = None;
+        }
+        self
+    }
 }
 
+/// Keep the longest prefix of `entries` that serializes within `remaining`
+/// bytes, counting a separating comma per entry, and return how many
+/// entries were dropped. Once anything is dropped nothing further fits.
+fn keep_within<T: Serialize>(entries: &mut Vec<T>, remaining: &mut usize) -> usize {
+    let kept = entries
+        .iter()
+        .take_while(|entry| {
+            let size = serde_json::to_string(entry).map_or(0, |s| s.len()) + 1;
+            let fits = size <= *remaining;
+            if fits {
+                *remaining -= size;
+            }
+            fits
+        })
+        .count();
+    let dropped = entries.len() - kept;
+    if dropped > 0 {
+        entries.truncate(kept);
+        *remaining = 0;
+    }
+    dropped
+}
+
+/// A [`SimulationResponse`] cut down to its outcome, for pipelines that
+/// have no use for logs, warnings or analysis.
 #[derive(Debug, Serialize)]
+pub struct QuietResponse<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<&'a serde_json::Value>,
+    pub status: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_value: Option<&'a ScValJson>,
+    /// Present only when `return_value` was dropped for its XDR.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_value_xdr: Option<&'a str>,
+    pub events: &'a [String],
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct DiagnosticEvent {
     pub event_type: String,
     pub contract_id: Option<String>,
@@ -84,13 +858,247 @@ pub struct DiagnosticEvent {
     pub wasm_instruction: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+/// Read-only and read-write ledger keys touched during invocation, each a
+/// base64 `LedgerKey`. Suitable for populating `SorobanTransactionData`.
+#[derive(Debug, Default, Serialize, JsonSchema)]
+pub struct LedgerFootprint {
+    pub read_only: Vec<String>,
+    pub read_write: Vec<String>,
+    /// `"persistent"` or `"temporary"` for each contract data and code key
+    /// above. The same contract key under both durabilities is two entries.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub durability: BTreeMap<String, String>,
+}
+
+/// Result of one host-function operation within the transaction.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct OperationResult {
+    /// Position of the operation in the envelope.
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_value: Option<ScValJson>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_value_xdr: Option<String>,
+    /// Contract events emitted while this operation ran.
+    pub events: Vec<ContractEventJson>,
+    /// Decoded host error, when this operation failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A classic operation found in the envelope. The simulator recognises
+/// these but does not apply them.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ClassicOperation {
+    /// Position of the operation in the envelope.
+    pub index: usize,
+    /// XDR operation name, e.g. `Payment`.
+    #[serde(rename = "type")]
+    pub operation_type: String,
+    /// Key fields such as destination, asset and amount (in stroops).
+    pub fields: BTreeMap<String, String>,
+    /// Whether the simulator executed the operation; always false today.
+    pub simulated: bool,
+    /// Result code the network recorded for the operation, e.g.
+    /// `PAYMENT_UNDERFUNDED`, when `result_meta_xdr` is a
+    /// `TransactionResultMeta` that carries operation results.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recorded_result: Option<String>,
+}
+
+/// An authorization entry that caused, or may have caused, an auth failure.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct AuthErrorJson {
+    /// Index of the operation whose `auth` list holds the entry.
+    pub operation_index: usize,
+    /// Position of the entry in that list; absent when no supplied entry
+    /// is at fault and the invocation needed one that was not given.
+    pub entry_index: Option<usize>,
+    /// `StrKey` of the address the entry authorizes for.
+    pub signer: Option<String>,
+    /// `expired`, `nonce_reused`, `missing_signature`, `bad_signature`,
+    /// `unknown_signer`, `insufficient_weight` or `unauthorized`.
+    pub reason: String,
+    pub detail: String,
+}
+
+/// What the network recorded for the transaction in `result_meta_xdr`, in
+/// the same shapes as the simulator's own results.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RecordedMeta {
+    /// `TransactionMeta` version the XDR was encoded with.
+    pub meta_version: u32,
+    pub return_value: Option<ScValJson>,
+    pub return_value_xdr: Option<String>,
+    /// Contract events emitted by the transaction.
+    pub events: Vec<ContractEventJson>,
+    /// Ledger entries the operations changed, TTL extensions excluded.
+    pub state_changes: Vec<StateChange>,
+}
+
+/// A `ledger_entries` pair that could not be decoded.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SkippedEntry {
+    /// The pair's key as given in the request.
+    pub key: String,
+    pub error: String,
+}
+
+/// One mismatch found in `diff` mode. `field` names what differs, e.g.
+/// `return_value`, `events[2]` or `state_changes[<base64 key>]`; a side
+/// that has no such item is `null`.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct MetaDifference {
+    pub field: String,
+    pub recorded: serde_json::Value,
+    pub simulated: serde_json::Value,
+}
+
+/// An authorization entry the host recorded as required, with its
+/// credentials left unsigned.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RecordedAuthJson {
+    /// Index of the operation that needs the entry.
+    pub operation_index: usize,
+    /// Base64 `SorobanAuthorizationEntry` XDR. Address credentials carry
+    /// the nonce to sign, a zero expiration ledger and no signature.
+    pub entry_xdr: String,
+    /// The entry's credentials, decoded.
+    pub credentials: AuthCredentialsJson,
+    /// `StrKey` of the address that must authorize; absent when the
+    /// transaction source account's signature covers it.
+    pub address: Option<String>,
+    pub nonce: Option<i64>,
+    /// The authorized invocation tree, decoded.
+    pub invocation: serde_json::Value,
+}
+
+/// A nonce an address-credentialed auth entry consumed, recorded as a
+/// temporary `ContractData` entry under the signer's address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct ConsumedNonce {
+    /// `G...` or `C...` `StrKey` of the signer.
+    pub address: String,
+    pub nonce: i64,
+}
+
+/// The credentials of a `SorobanAuthorizationEntry`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct AuthCredentialsJson {
+    /// `source_account` when the transaction or operation source account's
+    /// signature covers the entry, otherwise `account_address` or
+    /// `contract_address` for the kind of address that signs it.
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    /// `G...` or `C...` `StrKey` of the signing address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<i64>,
+    /// Last ledger the signature is valid in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature_expiration_ledger: Option<u32>,
+}
+
+/// A ledger entry the simulation created, updated or deleted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct StateChange {
+    /// Base64 `LedgerKey`.
+    pub key: String,
+    /// `"persistent"` or `"temporary"` for contract data and code.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub durability: Option<String>,
+    /// Base64 `LedgerEntry` before the simulation; `null` when created.
+    pub before: Option<String>,
+    /// Base64 `LedgerEntry` after the simulation; `null` when deleted.
+    pub after: Option<String>,
+}
+
+/// A transaction source account, with the ID of a muxed `M...` account
+/// split from the account it multiplexes.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SourceAccountJson {
+    /// `G...` `StrKey` of the underlying ed25519 account.
+    pub address: String,
+    /// Multiplexing ID, when the source is a muxed account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub muxed_id: Option<u64>,
+}
+
+/// The ledger a simulation ran in, after defaults and `ledger_info`
+/// overrides were applied.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct EnvironmentJson {
+    pub protocol_version: u32,
+    pub sequence_number: u32,
+    /// Ledger close time, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub network_passphrase: String,
+}
+
+/// What the invoked contract's instance entry holds.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct InstanceInfo {
+    /// The contract, as a `C...` `StrKey`.
+    pub contract_id: String,
+    /// Hex SHA-256 of the Wasm the instance runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wasm_hash: Option<String>,
+    /// For a Stellar Asset Contract, the asset from its metadata, e.g.
+    /// `native` or `USDC:G...`, or `stellar_asset` if it has none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    /// Instance storage, in the order the ledger keeps it.
+    pub storage: Vec<InstanceStorageEntry>,
+}
+
+/// One key/value pair of a contract's instance storage.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct InstanceStorageEntry {
+    pub key: ScValJson,
+    pub value: ScValJson,
+}
+
+/// A TTL moved by an `ExtendFootprintTtl` or `RestoreFootprint` operation.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TtlChange {
+    pub operation_index: usize,
+    /// Base64 `LedgerKey`.
+    pub key: String,
+    /// Last ledger the entry is live in after the operation.
+    pub live_until_ledger: u32,
+}
+
+/// A contract event decoded into JSON-friendly fields.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ContractEventJson {
+    /// Position in the order the host emitted events across the whole
+    /// transaction, diagnostic events included.
+    pub index: usize,
+    /// One of `contract`, `system` or `diagnostic`.
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// Emitting contract as a `C...` `StrKey`, if any.
+    pub contract_id: Option<String>,
+    /// The first topic when it is a symbol, which by convention names the
+    /// event, e.g. `transfer` or `mint`.
+    pub event_name: Option<String>,
+    pub topics: Vec<ScValJson>,
+    pub data: ScValJson,
+    /// False when the event was emitted by a call that failed and was
+    /// rolled back, e.g. under `try_call`.
+    pub in_successful_contract_call: bool,
+    /// Base64 XDR of the `ContractEvent`, for consumers that re-decode.
+    pub xdr: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct CategorizedEvent {
     pub category: String,
     pub event: DiagnosticEvent,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct BudgetUsage {
     pub cpu_instructions: u64,
     pub memory_bytes: u64,
@@ -101,7 +1109,30 @@ pub struct BudgetUsage {
     pub memory_usage_percent: f64,
 }
 
-#[derive(Debug, Serialize)]
+/// CPU and memory consumed by the host, with a per-cost-type breakdown.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ResourceUsage {
+    pub cpu_instructions: u64,
+    pub memory_bytes: u64,
+    /// Serialized size of the footprint entries loaded from the snapshot.
+    /// Contract code and instance entries usually make up most of it.
+    pub read_bytes: u32,
+    /// Serialized size of the read-write entries as the simulation left
+    /// them; deleted entries count for nothing.
+    pub write_bytes: u32,
+    /// Only cost types with at least one charged iteration are listed.
+    pub cost_breakdown: Vec<CostTypeUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct CostTypeUsage {
+    pub cost_type: String,
+    pub iterations: u64,
+    pub cpu_instructions: u64,
+    pub memory_bytes: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct StructuredError {
     pub error_type: String,
     pub message: String,
@@ -0,0 +1,385 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Structural checks on a transaction envelope before it reaches the host.
+//!
+//! The host rejects malformed invocations too, but only after setting up
+//! storage and metering, and with errors that do not say which operation
+//! was at fault. These checks catch the common mistakes up front and name
+//! the offending operation by index.
+
+use soroban_env_host::xdr::{
+    ContractExecutable, ContractIdPreimage, CreateContractArgs, CreateContractArgsV2,
+    FeeBumpTransactionInnerTx, HostFunction, Operation, OperationBody, ScAddress, ScVal,
+    TransactionEnvelope, TransactionExt,
+};
+
+/// Why a `TxV0` envelope is never simulated: the format predates Soroban.
+pub const TX_V0_NOT_SOROBAN: &str = "TxV0 envelopes cannot contain Soroban host functions";
+
+/// Check every operation in `envelope`, returning a message naming the
+/// first malformed one.
+///
+/// # Errors
+/// Describes the first malformed operation.
+pub fn validate_envelope(envelope: &TransactionEnvelope) -> Result<(), String> {
+    let (operations, has_soroban_data): (&[Operation], bool) = match envelope {
+        TransactionEnvelope::Tx(tx_v1) => (
+            &tx_v1.tx.operations,
+            matches!(tx_v1.tx.ext, TransactionExt::V1(_)),
+        ),
+        TransactionEnvelope::TxV0(tx_v0) => (&tx_v0.tx.operations, false),
+        TransactionEnvelope::TxFeeBump(bump) => match &bump.tx.inner_tx {
+            FeeBumpTransactionInnerTx::Tx(tx_v1) => (
+                &tx_v1.tx.operations,
+                matches!(tx_v1.tx.ext, TransactionExt::V1(_)),
+            ),
+        },
+    };
+
+    if matches!(envelope, TransactionEnvelope::TxV0(_)) {
+        if let Some(index) = operations.iter().position(is_soroban_operation) {
+            return Err(format!("Invalid operation {index}: {TX_V0_NOT_SOROBAN}"));
+        }
+    }
+
+    for (index, op) in operations.iter().enumerate() {
+        let needs_footprint = matches!(
+            op.body,
+            OperationBody::RestoreFootprint(_) | OperationBody::ExtendFootprintTtl(_)
+        );
+        if needs_footprint && !has_soroban_data {
+            return Err(format!(
+                "Invalid operation {index}: {} needs Soroban transaction data declaring its footprint",
+                op.body.name()
+            ));
+        }
+        validate_operation(op).map_err(|e| format!("Invalid operation {index}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Check that `envelope` holds at least one operation. An empty envelope
+/// is otherwise simulated, with a warning, as doing nothing.
+///
+/// # Errors
+/// Says the envelope has no operations.
+pub fn validate_has_operations(envelope: &TransactionEnvelope) -> Result<(), String> {
+    let empty = match envelope {
+        TransactionEnvelope::Tx(tx_v1) => tx_v1.tx.operations.is_empty(),
+        TransactionEnvelope::TxV0(tx_v0) => tx_v0.tx.operations.is_empty(),
+        TransactionEnvelope::TxFeeBump(bump) => match &bump.tx.inner_tx {
+            FeeBumpTransactionInnerTx::Tx(tx_v1) => tx_v1.tx.operations.is_empty(),
+        },
+    };
+    if empty {
+        return Err("Invalid envelope: transaction has no operations".to_string());
+    }
+    Ok(())
+}
+
+/// Check that no `ExtendFootprintTtl` operation reaches further than
+/// `max_entry_ttl` allows; the network rejects such operations as malformed.
+///
+/// # Errors
+/// Describes the first operation that extends too far.
+pub fn validate_extend_ttl(operations: &[Operation], max_entry_ttl: u32) -> Result<(), String> {
+    let limit = max_entry_ttl.saturating_sub(1);
+    for (index, op) in operations.iter().enumerate() {
+        if let OperationBody::ExtendFootprintTtl(extend) = &op.body {
+            if extend.extend_to > limit {
+                return Err(format!(
+                    "Invalid operation {index}: ExtendFootprintTtl extend_to {} exceeds the maximum of {limit} ledgers",
+                    extend.extend_to
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+const fn is_soroban_operation(op: &Operation) -> bool {
+    matches!(
+        op.body,
+        OperationBody::InvokeHostFunction(_)
+            | OperationBody::RestoreFootprint(_)
+            | OperationBody::ExtendFootprintTtl(_)
+    )
+}
+
+fn validate_operation(op: &Operation) -> Result<(), String> {
+    let OperationBody::InvokeHostFunction(invoke) = &op.body else {
+        return Ok(());
+    };
+    let args = match &invoke.host_function {
+        HostFunction::InvokeContract(args) => args,
+        HostFunction::CreateContract(CreateContractArgs {
+            contract_id_preimage,
+            executable,
+        })
+        | HostFunction::CreateContractV2(CreateContractArgsV2 {
+            contract_id_preimage,
+            executable,
+            ..
+        }) => return validate_create(contract_id_preimage, executable),
+        HostFunction::UploadContractWasm(_) => return Ok(()),
+    };
+
+    match &args.contract_address {
+        ScAddress::Contract(id) if id.0 .0 == [0u8; 32] => {
+            return Err("contract address is empty (all-zero contract ID)".to_string());
+        }
+        ScAddress::Contract(_) => {}
+        other => {
+            return Err(format!(
+                "contract address must be a contract, got {}",
+                address_kind(other)
+            ));
+        }
+    }
+
+    let function = args.function_name.to_utf8_string_lossy();
+    if !is_valid_symbol(&function) {
+        return Err(format!(
+            "function name {function:?} is not a valid Symbol (expected 1-32 characters from [a-zA-Z0-9_])"
+        ));
+    }
+
+    for (arg_index, arg) in args.args.iter().enumerate() {
+        validate_arg(arg).map_err(|e| format!("argument {arg_index} of {function}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// An asset preimage derives the address of the built-in Stellar Asset
+/// Contract, so it cannot deploy Wasm.
+fn validate_create(
+    preimage: &ContractIdPreimage,
+    executable: &ContractExecutable,
+) -> Result<(), String> {
+    match (preimage, executable) {
+        (ContractIdPreimage::Asset(_), ContractExecutable::Wasm(_)) => Err(
+            "an asset contract ID preimage needs the stellar_asset executable, not Wasm"
+                .to_string(),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Reject values that are well-typed XDR but can never be passed to a
+/// contract: host-internal ledger key markers, and absent vec/map bodies.
+fn validate_arg(val: &ScVal) -> Result<(), String> {
+    match val {
+        ScVal::Symbol(sym) => {
+            let s = sym.to_utf8_string_lossy();
+            if is_valid_symbol(&s) {
+                Ok(())
+            } else {
+                Err(format!("symbol {s:?} contains invalid characters"))
+            }
+        }
+        ScVal::Vec(None) => Err("vec has no body".to_string()),
+        ScVal::Vec(Some(items)) => items.iter().try_for_each(validate_arg),
+        ScVal::Map(None) => Err("map has no body".to_string()),
+        ScVal::Map(Some(entries)) => entries.iter().try_for_each(|entry| {
+            validate_arg(&entry.key)?;
+            validate_arg(&entry.val)
+        }),
+        ScVal::LedgerKeyContractInstance | ScVal::LedgerKeyNonce(_) | ScVal::ContractInstance(_) => {
+            Err(format!("{} values cannot be passed to a contract", val.name()))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn is_valid_symbol(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= 32
+        && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+const fn address_kind(address: &ScAddress) -> &'static str {
+    match address {
+        ScAddress::Account(_) => "an account",
+        ScAddress::MuxedAccount(_) => "a muxed account",
+        ScAddress::ClaimableBalance(_) => "a claimable balance",
+        ScAddress::LiquidityPool(_) => "a liquidity pool",
+        ScAddress::Contract(_) => "a contract",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_env_host::xdr::{ExtensionPoint, VecM};
+    use soroban_env_host::xdr::{
+        AccountId, ContractId, Hash, InvokeContractArgs, InvokeHostFunctionOp, Memo,
+        MuxedAccount, Preconditions, PublicKey, RestoreFootprintOp, ScSymbol, SequenceNumber,
+        StringM, Transaction, TransactionV1Envelope, Uint256,
+    };
+
+    fn envelope_with_ops(ops: Vec<Operation>) -> TransactionEnvelope {
+        TransactionEnvelope::Tx(TransactionV1Envelope {
+            tx: Transaction {
+                source_account: MuxedAccount::Ed25519(Uint256([0u8; 32])),
+                fee: 100,
+                seq_num: SequenceNumber(1),
+                cond: Preconditions::None,
+                memo: Memo::None,
+                operations: ops.try_into().unwrap(),
+                ext: TransactionExt::V0,
+            },
+            signatures: VecM::default(),
+        })
+    }
+
+    fn invoke_op(address: ScAddress, function: &str, args: Vec<ScVal>) -> Operation {
+        Operation {
+            source_account: None,
+            body: OperationBody::InvokeHostFunction(InvokeHostFunctionOp {
+                host_function: HostFunction::InvokeContract(InvokeContractArgs {
+                    contract_address: address,
+                    function_name: ScSymbol(StringM::try_from(function).unwrap()),
+                    args: args.try_into().unwrap(),
+                }),
+                auth: VecM::default(),
+            }),
+        }
+    }
+
+    fn contract() -> ScAddress {
+        ScAddress::Contract(ContractId(Hash([7u8; 32])))
+    }
+
+    #[test]
+    fn test_well_formed_invocation_passes() {
+        let op = invoke_op(contract(), "transfer", vec![ScVal::U32(1)]);
+        assert!(validate_envelope(&envelope_with_ops(vec![op])).is_ok());
+    }
+
+    #[test]
+    fn test_empty_operations_rejected() {
+        let envelope = envelope_with_ops(vec![]);
+        assert!(validate_envelope(&envelope).is_ok());
+        let err = validate_has_operations(&envelope).unwrap_err();
+        assert!(err.contains("no operations"), "{err}");
+    }
+
+    #[test]
+    fn test_zero_contract_address_names_operation() {
+        let good = invoke_op(contract(), "hello", vec![]);
+        let bad = invoke_op(ScAddress::Contract(ContractId(Hash([0u8; 32]))), "hello", vec![]);
+        let err = validate_envelope(&envelope_with_ops(vec![good, bad])).unwrap_err();
+        assert!(err.starts_with("Invalid operation 1:"), "{err}");
+        assert!(err.contains("empty"), "{err}");
+    }
+
+    #[test]
+    fn test_account_address_rejected() {
+        let account = ScAddress::Account(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(
+            [1u8; 32],
+        ))));
+        let err = validate_envelope(&envelope_with_ops(vec![invoke_op(account, "hello", vec![])]))
+            .unwrap_err();
+        assert!(err.contains("got an account"), "{err}");
+    }
+
+    #[test]
+    fn test_invalid_function_name_rejected() {
+        for name in ["", "has space", "dash-ed"] {
+            let op = invoke_op(contract(), name, vec![]);
+            let err = validate_envelope(&envelope_with_ops(vec![op])).unwrap_err();
+            assert!(err.contains("not a valid Symbol"), "{name:?}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_asset_preimage_with_wasm_rejected() {
+        use soroban_env_host::xdr::Asset;
+
+        let create = |executable| Operation {
+            source_account: None,
+            body: OperationBody::InvokeHostFunction(InvokeHostFunctionOp {
+                host_function: HostFunction::CreateContract(CreateContractArgs {
+                    contract_id_preimage: ContractIdPreimage::Asset(Asset::Native),
+                    executable,
+                }),
+                auth: VecM::default(),
+            }),
+        };
+        let sac = create(ContractExecutable::StellarAsset);
+        assert!(validate_envelope(&envelope_with_ops(vec![sac])).is_ok());
+
+        let wasm = create(ContractExecutable::Wasm(Hash([1u8; 32])));
+        let err = validate_envelope(&envelope_with_ops(vec![wasm])).unwrap_err();
+        assert!(err.contains("needs the stellar_asset executable"), "{err}");
+    }
+
+    #[test]
+    fn test_restore_without_soroban_data_rejected() {
+        let op = Operation {
+            source_account: None,
+            body: OperationBody::RestoreFootprint(RestoreFootprintOp {
+                ext: ExtensionPoint::default(),
+            }),
+        };
+        let err = validate_envelope(&envelope_with_ops(vec![op])).unwrap_err();
+        assert!(err.starts_with("Invalid operation 0: RestoreFootprint"), "{err}");
+    }
+
+    #[test]
+    fn test_extend_past_max_ttl_rejected() {
+        use soroban_env_host::xdr::{ExtendFootprintTtlOp, ExtensionPoint};
+
+        let extend = |extend_to| Operation {
+            source_account: None,
+            body: OperationBody::ExtendFootprintTtl(ExtendFootprintTtlOp {
+                ext: ExtensionPoint::V0,
+                extend_to,
+            }),
+        };
+        assert!(validate_extend_ttl(&[extend(999)], 1_000).is_ok());
+        let err = validate_extend_ttl(&[extend(10), extend(1_000)], 1_000).unwrap_err();
+        assert!(err.starts_with("Invalid operation 1:"), "{err}");
+        assert!(err.contains("maximum of 999 ledgers"), "{err}");
+    }
+
+    #[test]
+    fn test_soroban_operation_in_tx_v0_rejected() {
+        use soroban_env_host::xdr::{TransactionV0, TransactionV0Envelope, TransactionV0Ext};
+
+        let envelope = TransactionEnvelope::TxV0(TransactionV0Envelope {
+            tx: TransactionV0 {
+                source_account_ed25519: Uint256([0u8; 32]),
+                fee: 100,
+                seq_num: SequenceNumber(1),
+                time_bounds: None,
+                memo: Memo::None,
+                operations: vec![invoke_op(contract(), "hello", vec![])].try_into().unwrap(),
+                ext: TransactionV0Ext::V0,
+            },
+            signatures: VecM::default(),
+        });
+        let err = validate_envelope(&envelope).unwrap_err();
+        assert_eq!(err, format!("Invalid operation 0: {TX_V0_NOT_SOROBAN}"));
+    }
+
+    #[test]
+    fn test_malformed_args_rejected() {
+        let cases = vec![
+            (ScVal::Vec(None), "vec has no body"),
+            (ScVal::Map(None), "map has no body"),
+            (ScVal::LedgerKeyContractInstance, "cannot be passed"),
+            (
+                ScVal::Vec(Some(vec![ScVal::Vec(None)].try_into().unwrap())),
+                "vec has no body",
+            ),
+        ];
+        for (arg, expected) in cases {
+            let op = invoke_op(contract(), "hello", vec![ScVal::U32(0), arg]);
+            let err = validate_envelope(&envelope_with_ops(vec![op])).unwrap_err();
+            assert!(err.contains("argument 1 of hello"), "{err}");
+            assert!(err.contains(expected), "{err}");
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Versions of the simulator and the Soroban host it links.
+//!
+//! A simulation is only as faithful as the host behind it, so `--version`
+//! reports which `soroban-env-host` is compiled in and the newest ledger
+//! protocol it implements. The host does not publish the oldest protocol
+//! it still supports, so only the maximum is reported.
+
+use serde::Serialize;
+
+/// What `--version` prints.
+#[derive(Debug, Serialize)]
+pub struct VersionInfo {
+    /// Version of this crate.
+    pub simulator: &'static str,
+    /// Version of the linked `soroban-env-host` crate.
+    pub soroban_env_host: &'static str,
+    /// Git revision the host was built from.
+    pub soroban_env_host_rev: &'static str,
+    /// Newest ledger protocol the host implements; simulations default to it.
+    pub max_protocol_version: u32,
+}
+
+/// Versions of this build.
+#[must_use]
+pub const fn version_info() -> VersionInfo {
+    VersionInfo {
+        simulator: env!("CARGO_PKG_VERSION"),
+        soroban_env_host: soroban_env_host::VERSION.pkg,
+        soroban_env_host_rev: soroban_env_host::VERSION.rev,
+        max_protocol_version: soroban_env_host::meta::INTERFACE_VERSION.protocol,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_info_matches_default_ledger() {
+        let info = version_info();
+        assert_eq!(info.simulator, env!("CARGO_PKG_VERSION"));
+        assert!(!info.soroban_env_host.is_empty());
+        assert_eq!(
+            info.max_protocol_version,
+            crate::runner::default_ledger_info().protocol_version
+        );
+    }
+}
@@ -22,7 +22,8 @@ pub fn enforce_soroban_compatibility(wasm: &[u8]) -> Result<(), String> {
     Ok(())
 }
 
-fn is_float_op(op: &Operator) -> bool {
+#[allow(clippy::enum_glob_use)]
+const fn is_float_op(op: &Operator) -> bool {
     use Operator::*;
     matches!(
         op,
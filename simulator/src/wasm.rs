@@ -17,10 +17,10 @@ pub enum WasmLoadError {
 impl std::fmt::Display for WasmLoadError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            WasmLoadError::Io(e) => write!(f, "failed to read WASM file: {}", e),
-            WasmLoadError::InvalidMagic => write!(f, "invalid WASM: missing magic bytes (\\0asm)"),
-            WasmLoadError::TooLarge { size, limit } => {
-                write!(f, "WASM too large: {} bytes (limit {})", size, limit)
+            Self::Io(e) => write!(f, "failed to read WASM file: {e}"),
+            Self::InvalidMagic => write!(f, "invalid WASM: missing magic bytes (\\0asm)"),
+            Self::TooLarge { size, limit } => {
+                write!(f, "WASM too large: {size} bytes (limit {limit})")
             }
         }
     }
@@ -57,3 +57,18 @@ pub fn load_wasm_from_path(path: &str) -> Result<Vec<u8>, WasmLoadError> {
 
     Ok(bytes)
 }
+
+/// Check `bytes` against the contract size limit and the magic number, so
+/// obviously unusable blobs are rejected before reaching the host.
+pub fn check_wasm_bytes(bytes: &[u8]) -> Result<(), WasmLoadError> {
+    if bytes.len() > MAX_WASM_SIZE {
+        return Err(WasmLoadError::TooLarge {
+            size: bytes.len(),
+            limit: MAX_WASM_SIZE,
+        });
+    }
+    if !bytes.starts_with(WASM_MAGIC) {
+        return Err(WasmLoadError::InvalidMagic);
+    }
+    Ok(())
+}
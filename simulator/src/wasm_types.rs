@@ -4,7 +4,9 @@
 //! WebAssembly type parsing and signature analysis for enhanced trap diagnostics.
 //!
 //! This module provides utilities to parse WebAssembly type sections and function tables,
-//! enabling detailed error messages when call_indirect traps occur.
+//! enabling detailed error messages when `call_indirect` traps occur.
+
+#![allow(dead_code)]
 
 use serde::Serialize;
 use wasmparser::{Parser, Payload, ValType};
@@ -22,19 +24,19 @@ pub enum ValueType {
 }
 
 impl ValueType {
-    /// Convert from wasmparser's ValType
-    fn from_valtype(vt: ValType) -> Self {
+    /// Convert from wasmparser's `ValType`
+    const fn from_valtype(vt: ValType) -> Self {
         match vt {
-            ValType::I32 => ValueType::I32,
-            ValType::I64 => ValueType::I64,
-            ValType::F32 => ValueType::F32,
-            ValType::F64 => ValueType::F64,
-            ValType::V128 => ValueType::V128,
+            ValType::I32 => Self::I32,
+            ValType::I64 => Self::I64,
+            ValType::F32 => Self::F32,
+            ValType::F64 => Self::F64,
+            ValType::V128 => Self::V128,
             ValType::Ref(rt) => {
                 if rt.is_func_ref() {
-                    ValueType::FuncRef
+                    Self::FuncRef
                 } else {
-                    ValueType::ExternRef
+                    Self::ExternRef
                 }
             }
         }
@@ -44,13 +46,13 @@ impl ValueType {
 impl std::fmt::Display for ValueType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ValueType::I32 => write!(f, "i32"),
-            ValueType::I64 => write!(f, "i64"),
-            ValueType::F32 => write!(f, "f32"),
-            ValueType::F64 => write!(f, "f64"),
-            ValueType::V128 => write!(f, "v128"),
-            ValueType::FuncRef => write!(f, "funcref"),
-            ValueType::ExternRef => write!(f, "externref"),
+            Self::I32 => write!(f, "i32"),
+            Self::I64 => write!(f, "i64"),
+            Self::F32 => write!(f, "f32"),
+            Self::F64 => write!(f, "f64"),
+            Self::V128 => write!(f, "v128"),
+            Self::FuncRef => write!(f, "funcref"),
+            Self::ExternRef => write!(f, "externref"),
         }
     }
 }
@@ -64,7 +66,7 @@ pub struct FunctionSignature {
 
 impl FunctionSignature {
     /// Create a new function signature
-    pub fn new(params: Vec<ValueType>, results: Vec<ValueType>) -> Self {
+    pub const fn new(params: Vec<ValueType>, results: Vec<ValueType>) -> Self {
         Self { params, results }
     }
 
@@ -75,7 +77,7 @@ impl FunctionSignature {
         } else {
             self.params
                 .iter()
-                .map(|t| t.to_string())
+                .map(std::string::ToString::to_string)
                 .collect::<Vec<_>>()
                 .join(", ")
         };
@@ -85,16 +87,16 @@ impl FunctionSignature {
         } else {
             self.results
                 .iter()
-                .map(|t| t.to_string())
+                .map(std::string::ToString::to_string)
                 .collect::<Vec<_>>()
                 .join(", ")
         };
 
-        format!("({}) -> ({})", params, results)
+        format!("({params}) -> ({results})")
     }
 
     /// Compare this signature with another and return detailed differences
-    pub fn compare(&self, other: &FunctionSignature) -> SignatureDiff {
+    pub fn compare(&self, other: &Self) -> SignatureDiff {
         let param_count_match = self.params.len() == other.params.len();
         let result_count_match = self.results.len() == other.results.len();
 
@@ -131,15 +133,15 @@ impl FunctionSignature {
 pub struct SignatureDiff {
     pub param_count_match: bool,
     pub result_count_match: bool,
-    /// (index, expected_type, actual_type)
+    /// (index, `expected_type`, `actual_type`)
     pub param_mismatches: Vec<(usize, ValueType, ValueType)>,
-    /// (index, expected_type, actual_type)
+    /// (index, `expected_type`, `actual_type`)
     pub result_mismatches: Vec<(usize, ValueType, ValueType)>,
 }
 
 impl SignatureDiff {
     /// Check if signatures are identical
-    pub fn is_match(&self) -> bool {
+    pub const fn is_match(&self) -> bool {
         self.param_count_match
             && self.result_count_match
             && self.param_mismatches.is_empty()
@@ -159,11 +161,11 @@ impl TypeSection {
         let mut types = Vec::new();
 
         for payload in Parser::new(0).parse_all(wasm_bytes) {
-            let payload = payload.map_err(|e| format!("Failed to parse WASM: {}", e))?;
+            let payload = payload.map_err(|e| format!("Failed to parse WASM: {e}"))?;
 
             if let Payload::TypeSection(type_reader) = payload {
                 for rec_group in type_reader {
-                    let rec_group = rec_group.map_err(|e| format!("Failed to read type: {}", e))?;
+                    let rec_group = rec_group.map_err(|e| format!("Failed to read type: {e}"))?;
 
                     // RecGroup contains SubType entries
                     for sub_type in rec_group.types() {
@@ -187,7 +189,7 @@ impl TypeSection {
             }
         }
 
-        Ok(TypeSection { types })
+        Ok(Self { types })
     }
 
     /// Get a function signature by type index
@@ -196,13 +198,13 @@ impl TypeSection {
     }
 
     /// Get the number of types in this section
-    pub fn len(&self) -> usize {
+    pub const fn len(&self) -> usize {
         self.types.len()
     }
 
     /// Check if the type section is empty
     #[allow(dead_code)]
-    pub fn is_empty(&self) -> bool {
+    pub const fn is_empty(&self) -> bool {
         self.types.is_empty()
     }
 }
@@ -308,7 +310,7 @@ mod tests {
     #[test]
     fn test_type_section_parse_simple_module() {
         // Simple WAT: (module (func (param i32) (result i64)))
-        let wasm = wat::parse_str(r#"(module (func (param i32) (result i64)))"#).unwrap();
+        let wasm = wat::parse_str(r"(module (func (param i32) (result i64)))").unwrap();
         let type_section = TypeSection::parse(&wasm).unwrap();
         assert_eq!(type_section.len(), 1);
         let sig = type_section.get_signature(0).unwrap();
@@ -319,12 +321,12 @@ mod tests {
     #[test]
     fn test_type_section_parse_multiple_types() {
         let wasm = wat::parse_str(
-            r#"
+            r"
             (module
                 (func (param i32) (result i64))
                 (func (param i64 i64) (result i32))
             )
-            "#,
+            ",
         )
         .unwrap();
         let type_section = TypeSection::parse(&wasm).unwrap();
@@ -341,7 +343,7 @@ mod tests {
 
     #[test]
     fn test_type_section_get_signature_out_of_bounds() {
-        let wasm = wat::parse_str(r#"(module (func (param i32)))"#).unwrap();
+        let wasm = wat::parse_str(r"(module (func (param i32)))").unwrap();
         let type_section = TypeSection::parse(&wasm).unwrap();
         assert!(type_section.get_signature(10).is_none());
     }
@@ -0,0 +1,70 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Depth and length caps for decoding XDR from requests.
+//!
+//! Request XDR is untrusted, and an unbounded decode of a deeply nested
+//! value can exhaust the stack or memory before it is rejected. Every
+//! request blob is decoded with [`limits`], which default to caps well above
+//! anything the network accepts. The binary's `--xdr-depth-limit` and
+//! `--xdr-length-limit` flags change them for the whole process.
+
+use soroban_env_host::xdr::{Error, Limits};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// Default nesting depth allowed when decoding request XDR.
+pub const DEFAULT_DEPTH_LIMIT: u32 = 500;
+
+/// Default number of bytes one request XDR blob may decode.
+pub const DEFAULT_LENGTH_LIMIT: usize = 16 * 1024 * 1024;
+
+static DEPTH_LIMIT: AtomicU32 = AtomicU32::new(DEFAULT_DEPTH_LIMIT);
+static LENGTH_LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_LENGTH_LIMIT);
+
+/// Decode request XDR with at most `depth` levels of nesting and `length`
+/// bytes per blob from now on, on every thread.
+pub fn set(depth: u32, length: usize) {
+    DEPTH_LIMIT.store(depth, Ordering::Relaxed);
+    LENGTH_LIMIT.store(length, Ordering::Relaxed);
+}
+
+/// The caps request XDR is decoded with.
+pub fn limits() -> Limits {
+    Limits {
+        depth: DEPTH_LIMIT.load(Ordering::Relaxed),
+        len: LENGTH_LIMIT.load(Ordering::Relaxed),
+    }
+}
+
+/// The depth cap alone, for streams such as bucket files whose total size
+/// is legitimately larger than any one blob.
+pub fn depth_limits() -> Limits {
+    Limits::depth(DEPTH_LIMIT.load(Ordering::Relaxed))
+}
+
+/// Whether decoding failed because it ran past [`limits`] rather than
+/// because the XDR is malformed.
+#[must_use]
+pub const fn is_limit_error(error: &Error) -> bool {
+    matches!(error, Error::DepthLimitExceeded | Error::LengthLimitExceeded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_env_host::xdr::{ReadXdr, ScVal, WriteXdr};
+
+    #[test]
+    fn test_oversized_blob_is_a_limit_error() {
+        let bytes = ScVal::Bytes(vec![7u8; 64].try_into().unwrap())
+            .to_xdr(Limits::none())
+            .unwrap();
+        let limits = Limits {
+            depth: DEFAULT_DEPTH_LIMIT,
+            len: 16,
+        };
+        let error = ScVal::from_xdr(&bytes, limits).unwrap_err();
+        assert!(is_limit_error(&error), "{error}");
+        assert!(!is_limit_error(&ScVal::from_xdr([0xff; 4], super::limits()).unwrap_err()));
+    }
+}
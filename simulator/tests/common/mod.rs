@@ -0,0 +1,59 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Helpers shared by the integration tests that drive the simulator binary.
+
+// Each test crate compiles this module on its own and uses only part of it.
+#![allow(dead_code)]
+
+use soroban_env_host::xdr::{Limits, ScEnvMetaEntry, ScEnvMetaEntryInterfaceVersion, WriteXdr};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Run the binary with `args` and nothing on stdin, returning its exit code
+/// and stdout.
+pub fn run(args: &[&str]) -> (i32, String) {
+    run_with_input(args, "")
+}
+
+/// Run the binary with `args` and `input` on stdin, returning its exit code
+/// and stdout.
+pub fn run_with_input(args: &[&str], input: &str) -> (i32, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_simulator"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to start simulator");
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().expect("simulator did not finish");
+    (
+        output.status.code().expect("simulator was killed"),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+/// Path of the file `name` under `tests/fixtures`.
+pub fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+/// An empty module carrying the interface version a contract must declare.
+pub fn contract_wasm() -> Vec<u8> {
+    let meta = ScEnvMetaEntry::ScEnvMetaKindInterfaceVersion(ScEnvMetaEntryInterfaceVersion {
+        protocol: soroban_env_host::meta::INTERFACE_VERSION.protocol,
+        pre_release: 0,
+    })
+    .to_xdr(Limits::none())
+    .unwrap();
+    let name = b"contractenvmetav0";
+
+    let mut wasm = wat::parse_str("(module)").unwrap();
+    wasm.push(0);
+    wasm.push(u8::try_from(1 + name.len() + meta.len()).unwrap());
+    wasm.push(u8::try_from(name.len()).unwrap());
+    wasm.extend_from_slice(name);
+    wasm.extend_from_slice(&meta);
+    wasm
+}
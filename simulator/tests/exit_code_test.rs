@@ -0,0 +1,54 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Exit status of the simulator binary for each response category.
+
+mod common;
+
+use base64::Engine as _;
+use common::{contract_wasm, run_with_input};
+
+fn validate_wasm_request(wasm: &[u8]) -> String {
+    serde_json::json!({
+        "mode": "validate_wasm",
+        "wasm_base64": base64::engine::general_purpose::STANDARD.encode(wasm),
+    })
+    .to_string()
+}
+
+#[test]
+fn test_success_exits_zero() {
+    let (code, stdout) = run_with_input(&[], &validate_wasm_request(&contract_wasm()));
+    assert_eq!(code, 0, "{stdout}");
+    assert!(stdout.contains("\"status\":\"success\""));
+}
+
+#[test]
+fn test_simulation_error_exits_one() {
+    let (code, stdout) = run_with_input(&[], &validate_wasm_request(b"\0asm"));
+    assert_eq!(code, 1, "{stdout}");
+    assert!(stdout.contains("INVALID_WASM"));
+}
+
+#[test]
+fn test_input_errors_exit_two() {
+    let (code, stdout) = run_with_input(&[], "this is not json");
+    assert_eq!(code, 2, "{stdout}");
+    assert!(stdout.contains("INVALID_JSON"));
+
+    let request = r#"{"envelope_xdr": "not base64!", "result_meta_xdr": "", "enable_optimization_advisor": false, "timestamp": ""}"#;
+    let (code, stdout) = run_with_input(&[], request);
+    assert_eq!(code, 2, "{stdout}");
+    assert!(stdout.contains("ENVELOPE_DECODE"));
+}
+
+#[test]
+fn test_batch_exits_with_worst_status() {
+    let batch = format!(
+        "[{}, {}]",
+        validate_wasm_request(&contract_wasm()),
+        validate_wasm_request(b"\0asm")
+    );
+    let (code, stdout) = run_with_input(&[], &batch);
+    assert_eq!(code, 1, "{stdout}");
+}
@@ -0,0 +1,13 @@
+;; Source of add.wasm, which is this module followed by a
+;; `contractenvmetav0` custom section declaring interface protocol 25.
+;;
+;; `add(a: u32, b: u32) -> u32`: both arguments carry the U32 tag (4) in
+;; their low byte, so adding them and dropping one tag leaves the tagged sum.
+(module
+    (func (export "add") (param i64 i64) (result i64)
+        local.get 0
+        local.get 1
+        i64.add
+        i64.const 4
+        i64.sub)
+    (memory (export "memory") 1))
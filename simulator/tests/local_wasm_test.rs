@@ -0,0 +1,74 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! Calling a contract straight from a local Wasm file with `--wasm`.
+
+mod common;
+
+use common::{fixture, run};
+
+/// A valid contract ID; nothing is deployed at it outside the simulation.
+const CONTRACT_ID: &str = "CA3D5KRYM6CB7OWQ6TWYRR3Z4T7GNZLKERYNZGGA5SOAOPIFY6YQGAXE";
+
+#[test]
+fn test_local_wasm_function_is_called() {
+    let wasm = fixture("add.wasm");
+    let (code, stdout) = run(&[
+        "--wasm",
+        &wasm,
+        "--id",
+        CONTRACT_ID,
+        "--fn",
+        "add",
+        "--arg",
+        r#"{"type":"u32","value":2}"#,
+        "--arg",
+        r#"{"type":"u32","value":40}"#,
+    ]);
+    assert_eq!(code, 0, "{stdout}");
+
+    let response: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(response["status"], "success");
+    assert_eq!(response["return_value"]["type"], "u32");
+    assert_eq!(response["return_value"]["value"], 42);
+    assert_eq!(response["read_only"], true);
+}
+
+#[test]
+fn test_missing_wasm_file_is_an_input_error() {
+    let (code, stdout) = run(&[
+        "--wasm",
+        &fixture("missing.wasm"),
+        "--id",
+        CONTRACT_ID,
+        "--fn",
+        "add",
+    ]);
+    assert_eq!(code, 2, "{stdout}");
+    assert!(stdout.contains("Failed to read Wasm file"));
+}
+
+#[test]
+fn test_trace_flag_builds_the_trace() {
+    let wasm = fixture("add.wasm");
+    let mut args = vec!["--wasm", &wasm, "--id", CONTRACT_ID, "--fn", "add"];
+    args.extend(["--arg", r#"{"type":"u32","value":2}"#]);
+    args.extend(["--arg", r#"{"type":"u32","value":40}"#]);
+
+    let (_, stdout) = run(&args);
+    let response: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert!(response.get("trace").is_none());
+
+    args.push("--trace");
+    let (code, stdout) = run(&args);
+    assert_eq!(code, 0, "{stdout}");
+    let response: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let kinds: Vec<&str> = response["trace"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["kind"].as_str().unwrap())
+        .collect();
+    assert!(kinds.contains(&"fn_call"), "{kinds:?}");
+    assert!(kinds.contains(&"storage_get"), "{kinds:?}");
+}
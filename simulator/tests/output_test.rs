@@ -0,0 +1,31 @@
+// Copyright 2025 Erst Users
+// SPDX-License-Identifier: Apache-2.0
+
+//! How the simulator binary terminates the response it writes.
+
+mod common;
+
+/// Run the binary with `args`, which must succeed, returning its stdout.
+fn run_ok(args: &[&str]) -> String {
+    let (code, stdout) = common::run(args);
+    assert_eq!(code, 0, "{stdout}");
+    stdout
+}
+
+#[test]
+fn test_response_ends_with_a_newline_by_default() {
+    let stdout = run_ok(&["--version"]);
+    assert!(stdout.ends_with("}\n"));
+}
+
+#[test]
+fn test_no_newline_writes_the_bare_json() {
+    let stdout = run_ok(&["--version", "--no-newline"]);
+    assert!(stdout.ends_with('}'));
+    serde_json::from_str::<serde_json::Value>(&stdout).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("response.json");
+    run_ok(&["--version", "--no-newline", "--output", path.to_str().unwrap()]);
+    assert!(std::fs::read(&path).unwrap().ends_with(b"}"));
+}